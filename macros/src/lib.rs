@@ -0,0 +1,596 @@
+//! # Panduza Platform Core Macros
+//!
+//! Derive macros re-exported by panduza-platform-core
+//!
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::punctuated::Punctuated;
+use syn::{
+    parenthesized, parse_macro_input, Data, DeriveInput, Expr, Fields, Ident, LitInt, LitStr,
+    Token, Type,
+};
+
+/// Derive the accessor models of a driver and its 'mount_all' function
+///
+/// Each field marked with `#[attribute(...)]` becomes an attribute of the driver.
+///
+/// ## Options
+///
+/// | Option                | Description                                          |
+/// |-----------------------|------------------------------------------------------|
+/// | name = "..."          | name of the attribute (default: name of the field)   |
+/// | mode = "rw"/"ro"/"wo" | mode of the attribute (default: from getter/setter)  |
+/// | kind = "..."          | boolean, number, string or enum (default: field type)|
+/// | info = "..."          | description of the attribute                         |
+/// | class = "..."         | class of the parent container holding the attribute  |
+/// | unit = "..."          | unit of a number                                     |
+/// | min = expr            | minimum of a number                                  |
+/// | max = expr            | maximum of a number                                  |
+/// | decimals = N          | decimals of a number                                 |
+/// | choices("a", "b")     | choices of an enum                                   |
+/// | getter = "method"     | `async fn method(&mut self) -> Result<T, Error>`     |
+/// | setter = "method"     | `async fn method(&mut self, v: T) -> Result<(), Error>` |
+///
+/// Without getter and setter the attribute reads the field itself and is read only.
+/// The structure is cloned for each task of the attributes, a write to the field
+/// would be lost, so a writable attribute needs a setter (the shared state of the
+/// driver, an `Arc<Mutex<_>>` for example, lives behind the getter and the setter).
+/// By default the mode follows the methods: "rw" with getter and setter, "ro" with
+/// only a getter and "wo" with only a setter.
+///
+#[proc_macro_derive(AccessorModel, attributes(attribute))]
+pub fn derive_accessor_model(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+/// Kind of attribute, it selects the accessor model
+///
+enum Kind {
+    Boolean,
+    Number,
+    String,
+    Enum,
+}
+
+/// Attribute declared on a field
+///
+struct AttributeField {
+    field: Ident,
+    name: String,
+    mode: String,
+    kind: Kind,
+    info: String,
+    class: Option<String>,
+    unit: Option<String>,
+    min: Option<Expr>,
+    max: Option<Expr>,
+    decimals: Option<LitInt>,
+    choices: Vec<LitStr>,
+    getter: Option<Ident>,
+    setter: Option<Ident>,
+
+    /// Index of the attribute in its accessor model
+    ///
+    index: usize,
+}
+
+impl AttributeField {
+    /// True if the mode allows to read the attribute
+    ///
+    fn readable(&self) -> bool {
+        self.mode != "wo"
+    }
+
+    /// True if the mode allows to write the attribute
+    ///
+    fn writable(&self) -> bool {
+        self.mode != "ro"
+    }
+
+    /// True if the attribute is backed by the field itself
+    ///
+    fn field_backed(&self) -> bool {
+        self.getter.is_none() && self.setter.is_none()
+    }
+}
+
+/// Guess the kind of attribute from the type of the field
+///
+fn kind_from_type(ty: &Type) -> Option<Kind> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let ident = path.path.segments.last()?.ident.to_string();
+    match ident.as_str() {
+        "bool" => Some(Kind::Boolean),
+        "String" => Some(Kind::String),
+        "f32" | "f64" | "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64"
+        | "usize" => Some(Kind::Number),
+        _ => None,
+    }
+}
+
+/// Parse the '#[attribute(...)]' of a field, None if the field is not an attribute
+///
+fn parse_field(field: &syn::Field) -> syn::Result<Option<AttributeField>> {
+    let Some(attr) = field.attrs.iter().find(|a| a.path().is_ident("attribute")) else {
+        return Ok(None);
+    };
+    let ident = field.ident.clone().unwrap();
+
+    let mut name = None;
+    let mut mode = None;
+    let mut kind = None;
+    let mut info = String::new();
+    let mut class = None;
+    let mut unit = None;
+    let mut min = None;
+    let mut max = None;
+    let mut decimals = None;
+    let mut choices = Vec::new();
+    let mut getter = None;
+    let mut setter = None;
+
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("choices") {
+            let content;
+            parenthesized!(content in meta.input);
+            choices = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?
+                .into_iter()
+                .collect();
+            return Ok(());
+        }
+        if meta.path.is_ident("min") {
+            min = Some(meta.value()?.parse::<Expr>()?);
+            return Ok(());
+        }
+        if meta.path.is_ident("max") {
+            max = Some(meta.value()?.parse::<Expr>()?);
+            return Ok(());
+        }
+        if meta.path.is_ident("decimals") {
+            decimals = Some(meta.value()?.parse::<LitInt>()?);
+            return Ok(());
+        }
+
+        let value = meta.value()?.parse::<LitStr>()?;
+        if meta.path.is_ident("name") {
+            name = Some(value.value());
+        } else if meta.path.is_ident("mode") {
+            if !["rw", "ro", "wo"].contains(&value.value().as_str()) {
+                return Err(syn::Error::new(value.span(), "mode must be rw, ro or wo"));
+            }
+            mode = Some(value.value());
+        } else if meta.path.is_ident("kind") {
+            kind = Some(match value.value().as_str() {
+                "boolean" => Kind::Boolean,
+                "number" => Kind::Number,
+                "string" => Kind::String,
+                "enum" => Kind::Enum,
+                _ => {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        "kind must be boolean, number, string or enum",
+                    ))
+                }
+            });
+        } else if meta.path.is_ident("info") {
+            info = value.value();
+        } else if meta.path.is_ident("class") {
+            class = Some(value.value());
+        } else if meta.path.is_ident("unit") {
+            unit = Some(value.value());
+        } else if meta.path.is_ident("getter") {
+            getter = Some(value.parse::<Ident>()?);
+        } else if meta.path.is_ident("setter") {
+            setter = Some(value.parse::<Ident>()?);
+        } else {
+            return Err(meta.error("unknown attribute option"));
+        }
+        Ok(())
+    })?;
+
+    //
+    // Resolve the kind
+    let kind = match kind.or_else(|| kind_from_type(&field.ty)) {
+        Some(Kind::String) if !choices.is_empty() => Kind::Enum,
+        Some(kind) => kind,
+        None => {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                "cannot guess the attribute kind from this type, add kind = \"...\"",
+            ))
+        }
+    };
+    if kind == Kind::Enum && choices.is_empty() {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "an enum requires choices(...)",
+        ));
+    }
+
+    //
+    // Default mode from the methods of the attribute
+    let mode = mode.unwrap_or_else(|| match (&getter, &setter) {
+        (Some(_), Some(_)) => "rw".to_string(),
+        (None, Some(_)) => "wo".to_string(),
+        _ => "ro".to_string(),
+    });
+
+    let att = AttributeField {
+        field: ident.clone(),
+        name: name.unwrap_or_else(|| ident.to_string()),
+        mode: mode,
+        kind: kind,
+        info: info,
+        class: class,
+        unit: unit,
+        min: min,
+        max: max,
+        decimals: decimals,
+        choices: choices,
+        getter: getter,
+        setter: setter,
+        index: 0,
+    };
+
+    //
+    // A write to a field is lost in the clone of the structure
+    if att.field_backed() && att.writable() {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "a field backed attribute is read only, add a setter (and a getter) to write it",
+        ));
+    }
+
+    //
+    // A method based attribute needs the methods its mode uses
+    if !att.field_backed() {
+        if att.readable() && att.getter.is_none() {
+            return Err(syn::Error::new_spanned(attr, "this mode requires a getter"));
+        }
+        if att.writable() && att.setter.is_none() {
+            return Err(syn::Error::new_spanned(attr, "this mode requires a setter"));
+        }
+    }
+
+    Ok(Some(att))
+}
+
+/// Generate the derive
+///
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input,
+            "AccessorModel can only be derived on structures",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input,
+            "AccessorModel requires named fields",
+        ));
+    };
+
+    //
+    // Collect the attributes and give them an index in their accessor model
+    let mut attributes = Vec::new();
+    let mut boolean_count = 0;
+    let mut number_count = 0;
+    let mut string_count = 0;
+    for field in fields.named.iter() {
+        if let Some(mut att) = parse_field(field)? {
+            let counter = match att.kind {
+                Kind::Boolean => &mut boolean_count,
+                Kind::Number => &mut number_count,
+                Kind::String | Kind::Enum => &mut string_count,
+            };
+            att.index = *counter;
+            *counter += 1;
+            attributes.push(att);
+        }
+    }
+
+    let booleans: Vec<&AttributeField> = attributes
+        .iter()
+        .filter(|a| a.kind == Kind::Boolean)
+        .collect();
+    let numbers: Vec<&AttributeField> = attributes
+        .iter()
+        .filter(|a| a.kind == Kind::Number)
+        .collect();
+    let strings: Vec<&AttributeField> = attributes
+        .iter()
+        .filter(|a| a.kind == Kind::String || a.kind == Kind::Enum)
+        .collect();
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut impls = TokenStream2::new();
+    if !booleans.is_empty() {
+        let get_arms = get_arms(&booleans, |v| quote! { #v }, |v| quote! { #v.clone() });
+        let set_arms = set_arms(&booleans, |v| quote! { #v });
+        impls.extend(quote! {
+            #[::panduza_platform_core::async_trait::async_trait]
+            impl #impl_generics ::panduza_platform_core::model::BooleanAccessorModel
+                for #name #ty_generics #where_clause
+            {
+                async fn get_boolean_at(
+                    &mut self,
+                    index: usize,
+                ) -> Result<bool, ::panduza_platform_core::Error> {
+                    match index {
+                        #(#get_arms)*
+                        _ => Err(::panduza_platform_core::Error::InvalidArgument(
+                            format!("Unknown boolean index {}", index),
+                        )),
+                    }
+                }
+                async fn set_boolean_at(
+                    &mut self,
+                    index: usize,
+                    value: bool,
+                ) -> Result<(), ::panduza_platform_core::Error> {
+                    match index {
+                        #(#set_arms)*
+                        _ => Err(::panduza_platform_core::Error::InvalidArgument(
+                            format!("Unknown boolean index {}", index),
+                        )),
+                    }
+                }
+            }
+        });
+    }
+    if !numbers.is_empty() {
+        let get_arms = get_arms(&numbers, |v| quote! { #v as f32 }, |v| quote! { #v as f32 });
+        let set_arms = set_arms(&numbers, |v| quote! { #v as _ });
+        impls.extend(quote! {
+            #[::panduza_platform_core::async_trait::async_trait]
+            impl #impl_generics ::panduza_platform_core::model::NumberAccessorModel
+                for #name #ty_generics #where_clause
+            {
+                async fn get_number_at(
+                    &mut self,
+                    index: usize,
+                ) -> Result<f32, ::panduza_platform_core::Error> {
+                    match index {
+                        #(#get_arms)*
+                        _ => Err(::panduza_platform_core::Error::InvalidArgument(
+                            format!("Unknown number index {}", index),
+                        )),
+                    }
+                }
+                async fn set_number_at(
+                    &mut self,
+                    index: usize,
+                    value: f32,
+                ) -> Result<(), ::panduza_platform_core::Error> {
+                    match index {
+                        #(#set_arms)*
+                        _ => Err(::panduza_platform_core::Error::InvalidArgument(
+                            format!("Unknown number index {}", index),
+                        )),
+                    }
+                }
+            }
+        });
+    }
+    if !strings.is_empty() {
+        let get_arms = get_arms(&strings, |v| quote! { #v }, |v| quote! { #v.clone() });
+        let set_arms = set_arms(&strings, |v| quote! { #v });
+        impls.extend(quote! {
+            #[::panduza_platform_core::async_trait::async_trait]
+            impl #impl_generics ::panduza_platform_core::model::StringAccessorModel
+                for #name #ty_generics #where_clause
+            {
+                async fn get_string_at(
+                    &mut self,
+                    index: usize,
+                ) -> Result<String, ::panduza_platform_core::Error> {
+                    match index {
+                        #(#get_arms)*
+                        _ => Err(::panduza_platform_core::Error::InvalidArgument(
+                            format!("Unknown string index {}", index),
+                        )),
+                    }
+                }
+                async fn set_string_at(
+                    &mut self,
+                    index: usize,
+                    value: String,
+                ) -> Result<(), ::panduza_platform_core::Error> {
+                    match index {
+                        #(#set_arms)*
+                        _ => Err(::panduza_platform_core::Error::InvalidArgument(
+                            format!("Unknown string index {}", index),
+                        )),
+                    }
+                }
+            }
+        });
+    }
+
+    let mount_all = mount_all(&attributes);
+    Ok(quote! {
+        #impls
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Create the classes and mount all the attributes of the driver in the container
+            ///
+            #[allow(unused_mut)]
+            pub async fn mount_all<C: ::panduza_platform_core::Container>(
+                &self,
+                mut parent: C,
+            ) -> Result<(), ::panduza_platform_core::Error> {
+                #mount_all
+                Ok(())
+            }
+        }
+    })
+}
+
+/// Match arms of the getter of an accessor model
+///
+/// 'from_getter' converts the value returned by the getter method,
+/// 'from_field' reads the value from the field.
+///
+fn get_arms(
+    attributes: &[&AttributeField],
+    from_getter: impl Fn(TokenStream2) -> TokenStream2,
+    from_field: impl Fn(TokenStream2) -> TokenStream2,
+) -> Vec<TokenStream2> {
+    attributes
+        .iter()
+        .map(|att| {
+            let index = att.index;
+            let name = &att.name;
+            let field = &att.field;
+            let body = if att.field_backed() {
+                let value = from_field(quote! { self.#field });
+                quote! { Ok(#value) }
+            } else if let Some(getter) = &att.getter {
+                let value = from_getter(quote! { v });
+                quote! { self.#getter().await.map(|v| #value) }
+            } else {
+                quote! {
+                    Err(::panduza_platform_core::Error::InvalidArgument(
+                        format!("Attribute '{}' is not readable", #name),
+                    ))
+                }
+            };
+            quote! { #index => #body, }
+        })
+        .collect()
+}
+
+/// Match arms of the setter of an accessor model
+///
+/// 'convert' turns the value of the accessor model into the type of the setter.
+///
+fn set_arms(
+    attributes: &[&AttributeField],
+    convert: impl Fn(TokenStream2) -> TokenStream2,
+) -> Vec<TokenStream2> {
+    attributes
+        .iter()
+        .map(|att| {
+            let index = att.index;
+            let name = &att.name;
+            let value = convert(quote! { value });
+            let body = if let Some(setter) = &att.setter {
+                quote! { self.#setter(#value).await }
+            } else {
+                quote! {
+                    Err(::panduza_platform_core::Error::InvalidArgument(
+                        format!("Attribute '{}' is not writable", #name),
+                    ))
+                }
+            };
+            quote! { #index => #body, }
+        })
+        .collect()
+}
+
+/// Body of the 'mount_all' function
+///
+fn mount_all(attributes: &[AttributeField]) -> TokenStream2 {
+    let mut body = TokenStream2::new();
+
+    //
+    // Create each class once, in the order of their first attribute
+    let mut classes: Vec<&String> = Vec::new();
+    for att in attributes.iter() {
+        if let Some(class) = &att.class {
+            if !classes.contains(&class) {
+                let var = format_ident!("class_{}", classes.len());
+                body.extend(quote! {
+                    let #var = parent.create_class(#class).finish().await?;
+                });
+                classes.push(class);
+            }
+        }
+    }
+
+    //
+    // Mount the attributes
+    for att in attributes.iter() {
+        let container = match &att.class {
+            Some(class) => {
+                let position = classes.iter().position(|c| *c == class).unwrap();
+                let var = format_ident!("class_{}", position);
+                quote! { #var.clone() }
+            }
+            None => quote! { parent.clone() },
+        };
+        let index = att.index;
+        let name = &att.name;
+        let info = &att.info;
+        let mode = match att.mode.as_str() {
+            "ro" => quote! { ::panduza_platform_core::AttributeMode::ReadOnly },
+            "wo" => quote! { ::panduza_platform_core::AttributeMode::WriteOnly },
+            _ => quote! { ::panduza_platform_core::AttributeMode::ReadWrite },
+        };
+        body.extend(match att.kind {
+            Kind::Boolean => quote! {
+                ::panduza_platform_core::template::attribute::boolean::mount_with_mode(
+                    #container, self.clone(), #index, #name, #info, #mode,
+                )
+                .await?;
+            },
+            Kind::String => quote! {
+                ::panduza_platform_core::template::attribute::string::mount_with_mode(
+                    #container, self.clone(), #index, #name, #info, #mode,
+                )
+                .await?;
+            },
+            Kind::Enum => {
+                let choices = &att.choices;
+                quote! {
+                    ::panduza_platform_core::template::attribute::r#enum::mount_with_mode(
+                        #container, self.clone(), #index, #name, #info,
+                        vec![#(#choices),*], #mode,
+                    )
+                    .await?;
+                }
+            }
+            Kind::Number => {
+                let min = option_tokens(att.min.as_ref().map(|m| quote! { (#m) as f64 }));
+                let max = option_tokens(att.max.as_ref().map(|m| quote! { (#m) as f64 }));
+                let unit = option_tokens(att.unit.as_ref().map(|u| quote! { #u.to_string() }));
+                let decimals = option_tokens(att.decimals.as_ref().map(|d| quote! { #d }));
+                quote! {
+                    ::panduza_platform_core::template::attribute::number::mount_with_settings(
+                        #container, self.clone(), #index, #name, #info, #mode,
+                        ::panduza_platform_core::instance::server::number::NumberSettings {
+                            min: #min,
+                            max: #max,
+                            unit: #unit,
+                            decimals: #decimals,
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+                }
+            }
+        });
+    }
+    body
+}
+
+/// Tokens of an Option
+///
+fn option_tokens(value: Option<TokenStream2>) -> TokenStream2 {
+    match value {
+        Some(v) => quote! { Some(#v) },
+        None => quote! { None },
+    }
+}
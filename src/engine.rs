@@ -1,14 +1,21 @@
+pub mod health;
 pub mod options;
+use health::{monitor_router_connection, Backoff, EngineHealth};
 use options::EngineOptions;
 
 use panduza::{
     pubsub::{self, new_connection}, // router::{DataReceiver, Router, RouterHandler},
 };
+use std::sync::Arc;
+use tokio::sync::watch;
 use zenoh::pubsub::Publisher;
 use zenoh::pubsub::Subscriber;
 use zenoh::sample::Sample;
 use zenoh::{handlers::FifoChannelHandler, Session};
 
+use crate::log_warn;
+use crate::Logger;
+
 /// The engine is the core object that will handle the connections and the events
 ///
 /// All the attribute and objects will be powered by the engine
@@ -22,6 +29,10 @@ pub struct Engine {
     /// Namespace of the engine
     ///
     pub namespace: Option<String>,
+
+    /// Health of the router connection, updated by the health monitor
+    ///
+    health: Arc<watch::Sender<EngineHealth>>,
 }
 
 impl Engine {
@@ -40,9 +51,33 @@ impl Engine {
         Self {
             session: session,
             namespace: namespace,
+            health: Arc::new(watch::channel(EngineHealth::Connected).0),
         }
     }
 
+    /// Receiver of the router connection health
+    ///
+    pub fn health(&self) -> watch::Receiver<EngineHealth> {
+        self.health.subscribe()
+    }
+
+    /// True if the router is reachable
+    ///
+    pub fn is_connected(&self) -> bool {
+        *self.health.borrow() == EngineHealth::Connected
+    }
+
+    /// Spawn the task that watches the router connection
+    ///
+    /// Not needed for loopback engines, they never lose their connection.
+    ///
+    pub fn start_health_monitor(&self) {
+        tokio::spawn(monitor_router_connection(
+            self.session.clone(),
+            self.health.clone(),
+        ));
+    }
+
     ///
     ///
     pub fn root_topic(&self, namespace: Option<String>) -> String {
@@ -128,6 +163,32 @@ impl Engine {
     // }
 }
 
+/// Create an engine on a peer-only zenoh session that stays inside the process
+///
+/// Scouting is disabled and no endpoint is opened, so only the entities
+/// declared on this session can talk to each other. Made for tests.
+///
+pub async fn new_loopback_engine(namespace: Option<String>) -> Result<Engine, String> {
+    let mut config = zenoh::Config::default();
+    for (key, value) in [
+        ("mode", r#""peer""#),
+        ("scouting/multicast/enabled", "false"),
+        ("scouting/gossip/enabled", "false"),
+        ("listen/endpoints", "[]"),
+        ("connect/endpoints", "[]"),
+    ] {
+        config
+            .insert_json5(key, value)
+            .map_err(|e| format!("loopback config '{}' ({})", key, e))?;
+    }
+
+    let session = zenoh::open(config).await.map_err(|e| e.to_string())?;
+
+    //
+    // Finalize the engine
+    Ok(Engine::new(session, namespace))
+}
+
 /// Create and Start the engine
 ///
 pub async fn new_engine(options: EngineOptions) -> Result<Engine, String> {
@@ -153,7 +214,19 @@ pub async fn new_engine(options: EngineOptions) -> Result<Engine, String> {
 /// Before starting a tokio context.
 ///
 pub struct EngineBuilder {
-    options: EngineOptions,
+    connection: EngineConnection,
+}
+
+/// Kind of session the builder will open
+///
+enum EngineConnection {
+    /// Client connection to a zenoh router
+    ///
+    Router(EngineOptions),
+
+    /// In-process peer session without any network access
+    ///
+    Loopback { namespace: Option<String> },
 }
 
 impl EngineBuilder {
@@ -164,16 +237,63 @@ impl EngineBuilder {
     ///
     pub fn new(options: EngineOptions) -> Self {
         Self {
-            // options: options,
-            options: options,
+            connection: EngineConnection::Router(options),
+        }
+    }
+
+    /// Prepare an engine that does not need any zenoh router
+    ///
+    /// See `new_loopback_engine`
+    ///
+    pub fn new_loopback(namespace: Option<String>) -> Self {
+        Self {
+            connection: EngineConnection::Loopback {
+                namespace: namespace,
+            },
         }
     }
 
+    /// True if the engine will not use the network
+    ///
+    pub fn is_loopback(&self) -> bool {
+        matches!(self.connection, EngineConnection::Loopback { .. })
+    }
+
+    /// Open the session and finalize the engine
+    ///
+    /// The router may not be started yet, so the connection is retried with
+    /// a backoff until it succeeds.
+    ///
     pub async fn build(self) -> Engine {
-        let namespace = self.options.pubsub_options.namespace.clone();
-        let session = new_connection(self.options.pubsub_options).await.unwrap();
-        //
-        // Finalize the engine
-        Engine::new(session, namespace)
+        match self.connection {
+            EngineConnection::Router(options) => {
+                let logger = Logger::new_for_runtime();
+                let namespace = options.pubsub_options.namespace.clone();
+                let mut backoff = Backoff::default();
+                let session = loop {
+                    match new_connection(options.pubsub_options.clone()).await {
+                        Ok(session) => break session,
+                        Err(e) => {
+                            let delay = backoff.next_delay();
+                            log_warn!(
+                                logger,
+                                "Router connection failed, retry in {:?} ({})",
+                                delay,
+                                e
+                            );
+                            tokio::time::sleep(delay).await;
+                        }
+                    }
+                };
+                //
+                // Finalize the engine
+                let engine = Engine::new(session, namespace);
+                engine.start_health_monitor();
+                engine
+            }
+            EngineConnection::Loopback { namespace } => {
+                new_loopback_engine(namespace).await.unwrap()
+            }
+        }
     }
 }
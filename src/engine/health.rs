@@ -0,0 +1,158 @@
+use crate::log_info;
+use crate::log_warn;
+use crate::Logger;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::sleep;
+use zenoh::Session;
+
+/// Period of the router checks while the connection is healthy
+///
+pub const HEALTH_CHECK_PERIOD: Duration = Duration::from_secs(1);
+
+/// First delay of the backoff
+///
+pub const BACKOFF_INITIAL_DELAY: Duration = Duration::from_millis(250);
+
+/// Max delay of the backoff
+///
+pub const BACKOFF_MAX_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Health of the connection between the engine and the router
+///
+pub enum EngineHealth {
+    /// At least one router is reachable
+    ///
+    Connected,
+
+    /// No router reachable, zenoh keeps trying to reconnect
+    ///
+    Lost,
+}
+
+/// Exponential backoff between two connection attempts
+///
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    /// Delay of the first attempt
+    ///
+    initial: Duration,
+
+    /// Delays never exceed this value
+    ///
+    max: Duration,
+
+    /// Delay of the next attempt
+    ///
+    current: Duration,
+}
+
+impl Backoff {
+    /// Create a new backoff
+    ///
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial: initial,
+            max: max,
+            current: initial,
+        }
+    }
+
+    /// Return the delay to wait before the next attempt and double the following one
+    ///
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = std::cmp::min(self.current * 2, self.max);
+        delay
+    }
+
+    /// Restart from the initial delay
+    ///
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(BACKOFF_INITIAL_DELAY, BACKOFF_MAX_DELAY)
+    }
+}
+
+/// Wait for the next health change and return the new health
+///
+/// Never returns if the monitor is gone, so it can be used safely in a select.
+///
+pub async fn wait_health_change(receiver: &mut watch::Receiver<EngineHealth>) -> EngineHealth {
+    if receiver.changed().await.is_err() {
+        std::future::pending::<()>().await;
+    }
+    *receiver.borrow_and_update()
+}
+
+/// Watch the routers of the session and publish the health of the connection
+///
+/// Checks are spaced with a backoff while the router is lost. The task ends
+/// when the session is closed.
+///
+pub async fn monitor_router_connection(
+    session: Session,
+    health: Arc<watch::Sender<EngineHealth>>,
+) -> Result<(), String> {
+    let logger = Logger::new_for_runtime();
+    let mut backoff = Backoff::default();
+    loop {
+        //
+        // The session is closed, nothing to monitor anymore
+        if session.is_closed() {
+            return Ok(());
+        }
+
+        //
+        // Client sessions only see routers, so no router means no connection
+        let connected = session.info().routers_zid().await.next().is_some();
+        let new_health = match connected {
+            true => EngineHealth::Connected,
+            false => EngineHealth::Lost,
+        };
+
+        //
+        // Publish only the changes
+        if *health.borrow() != new_health {
+            match new_health {
+                EngineHealth::Connected => log_info!(logger, "Router connection restored"),
+                EngineHealth::Lost => log_warn!(logger, "Router connection lost"),
+            }
+            health.send_replace(new_health);
+        }
+
+        //
+        // Wait before the next check
+        let delay = match new_health {
+            EngineHealth::Connected => {
+                backoff.reset();
+                HEALTH_CHECK_PERIOD
+            }
+            EngineHealth::Lost => backoff.next_delay(),
+        };
+        sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(350));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(200));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(350));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(350));
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+    }
+}
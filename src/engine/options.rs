@@ -1,6 +1,20 @@
 use panduza::pubsub;
+use serde::{Deserialize, Serialize};
+use std::ffi::{c_char, CStr};
 use std::fmt::Debug;
 
+/// Default address of the router
+///
+pub static DEFAULT_ROUTER_IP: &str = "127.0.0.1";
+
+/// Default port of the router
+///
+pub static DEFAULT_ROUTER_PORT: u16 = 7447;
+
+/// Default root certificate of the router
+///
+pub static DEFAULT_ROOT_CA_CERTIFICATE: &str = "minica.pem";
+
 #[derive(Debug)]
 /// Options of the platform engine
 ///
@@ -10,6 +24,58 @@ pub struct EngineOptions {
     pub pubsub_options: pubsub::Options,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+/// Json version of the engine options, each missing field takes its default value
+///
+/// ## Json Structure
+///
+/// {
+///     "ip": "127.0.0.1",
+///     "port": 7447,
+///     "root_ca_certificate": "minica.pem",
+///     "connect_certificate": "",
+///     "connect_private_key": "",
+///     "namespace": null
+/// }
+///
+struct EngineOptionsJson {
+    ip: String,
+    port: u16,
+    root_ca_certificate: String,
+    connect_certificate: String,
+    connect_private_key: String,
+    namespace: Option<String>,
+}
+
+impl EngineOptionsJson {
+    /// Convert into engine options
+    ///
+    fn into_options(self) -> EngineOptions {
+        EngineOptions::new(
+            self.ip,
+            self.port,
+            self.root_ca_certificate,
+            self.connect_certificate,
+            self.connect_private_key,
+            self.namespace,
+        )
+    }
+}
+
+impl Default for EngineOptionsJson {
+    fn default() -> Self {
+        Self {
+            ip: DEFAULT_ROUTER_IP.to_string(),
+            port: DEFAULT_ROUTER_PORT,
+            root_ca_certificate: DEFAULT_ROOT_CA_CERTIFICATE.to_string(),
+            connect_certificate: String::new(),
+            connect_private_key: String::new(),
+            namespace: None,
+        }
+    }
+}
+
 impl EngineOptions {
     pub fn new<T: Into<String> + Debug>(
         ip: T,
@@ -31,4 +97,62 @@ impl EngineOptions {
             },
         }
     }
+
+    /// Build options from a json string (see `EngineOptionsJson` for the structure)
+    ///
+    pub fn from_json_str(json: &str) -> Result<Self, crate::Error> {
+        let config: EngineOptionsJson = serde_json::from_str(json).map_err(|e| {
+            crate::Error::BadSettings(format!("Invalid engine configuration: {:?}", e))
+        })?;
+        Ok(config.into_options())
+    }
+
+    /// Build options from a C-style json string pointer
+    ///
+    /// A null pointer gives the default options.
+    ///
+    pub fn from_c_str_ptr(c_str: *const c_char) -> Result<Self, crate::Error> {
+        //
+        //
+        if c_str.is_null() {
+            return Ok(Self::default());
+        }
+
+        //
+        //
+        let c_str = unsafe { CStr::from_ptr(c_str) };
+        let str = c_str
+            .to_str()
+            .map_err(|e| crate::Error::InvalidArgument(format!("Invalid C string: {:?}", e)))?;
+
+        Self::from_json_str(str)
+    }
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        EngineOptionsJson::default().into_options()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json_str() {
+        let options = EngineOptions::from_json_str(
+            r#"{ "ip": "192.168.1.10", "port": 7448, "namespace": "bench" }"#,
+        )
+        .unwrap();
+        assert_eq!(options.pubsub_options.ip, "192.168.1.10");
+        assert_eq!(options.pubsub_options.port, 7448);
+        assert_eq!(
+            options.pubsub_options.root_ca_certificate,
+            DEFAULT_ROOT_CA_CERTIFICATE
+        );
+        assert_eq!(options.pubsub_options.namespace, Some("bench".to_string()));
+
+        assert!(EngineOptions::from_json_str(r#"{ "port": "x" }"#).is_err());
+    }
 }
@@ -28,6 +28,8 @@ pub enum Error {
     PluginError(String),
     #[error("Error managing a cross task channel")]
     ChannelError(String),
+    #[error("Operation timed out")]
+    Timeout(String),
     #[error("Error")]
     Generic(String),
 
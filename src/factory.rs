@@ -1,8 +1,10 @@
 pub mod producer;
 pub use producer::Producer;
 pub mod production_order;
+pub mod scanner;
+pub use scanner::Scanner;
 pub mod store;
-use crate::{Engine, Instance, Logger, Notification, ProductionOrder};
+use crate::{log_debug, log_warn, Engine, Error, Instance, Logger, Notification, ProductionOrder};
 use std::{collections::HashMap, ffi::CString};
 use store::{Product, Store};
 use tokio::sync::mpsc::Sender;
@@ -14,6 +16,8 @@ pub struct Factory {
     logger: Logger,
     /// List of known producers
     producers: HashMap<String, Box<dyn Producer>>,
+    /// True if settings keys not described by the producer props must be rejected
+    reject_unknown_settings: bool,
 }
 
 impl Factory {
@@ -24,6 +28,7 @@ impl Factory {
         let obj = Factory {
             logger: Logger::new_for_factory(),
             producers: HashMap::new(),
+            reject_unknown_settings: false,
         };
         // Info log
         obj.logger.info("# Device factory initialization");
@@ -39,6 +44,14 @@ impl Factory {
         }
     }
 
+    /// Reject production orders with settings keys not described by the producer props
+    ///
+    /// By default those keys are only reported in the logs
+    ///
+    pub fn set_reject_unknown_settings(&mut self, reject: bool) {
+        self.reject_unknown_settings = reject;
+    }
+
     /// Add a single producer
     pub fn add_producer(&mut self, producer: Box<dyn Producer>) {
         // Info log
@@ -99,85 +112,152 @@ impl Factory {
             .map_err(|e| crate::Error::InternalLogic(format!("Failed to build CString ({:?})", e)))
     }
 
+    /// Build a scan machine with the scanners provided by the producers
+    ///
+    pub fn scan_machine(&self) -> ScanMachine {
+        let mut scan_machine = ScanMachine::new();
+        for producer in self.producers.values() {
+            if let Some(scanner) = producer.scanner() {
+                scan_machine.add_scanner(scanner);
+            }
+        }
+        scan_machine
+    }
+
     /// production_order => json with ref, name, settings
     ///
+    /// The settings are checked against the producer props and completed with
+    /// the default values before the instance is created.
+    ///
     pub fn produce(
         &self,
         engine: Engine,
         production_order: ProductionOrder,
         notification_channel: Sender<Notification>,
         namespace: Option<String>,
-    ) -> Instance {
-        let producer = self.producers.get(production_order.dref()).unwrap();
-        let instance_actions = producer.produce().unwrap();
+    ) -> Result<Instance, Error> {
+        let producer =
+            self.producers
+                .get(production_order.dref())
+                .ok_or(Error::InvalidArgument(format!(
+                    "Unknown driver reference '{}'",
+                    production_order.dref()
+                )))?;
 
-        Instance::new(
+        //
+        // Check settings
+        let props = producer.props();
+        if !self.reject_unknown_settings {
+            for key in props.unknown_keys(&production_order.settings) {
+                log_warn!(
+                    self.logger,
+                    "'{}' unknown setting '{}'",
+                    production_order.name,
+                    key
+                );
+            }
+        }
+        let settings = props
+            .validate(production_order.settings, self.reject_unknown_settings)
+            .map_err(|e| match e {
+                Error::BadSettings(issues) => {
+                    Error::BadSettings(format!("'{}' settings: {}", production_order.name, issues))
+                }
+                other => other,
+            })?;
+
+        let instance_actions = producer.produce()?;
+        let reboot_policy = production_order
+            .reboot_policy
+            .unwrap_or_else(|| producer.reboot_policy());
+
+        Ok(Instance::new(
             engine.clone(),
             production_order.name,
             instance_actions,
-            production_order.settings,
+            Some(settings),
             notification_channel,
             namespace,
-        )
+            reboot_policy,
+        ))
     }
 }
 
-// ///
-// ///
-// pub struct ScanMachine {
-//     /// Local logger
-//     logger: Logger,
-//     ///
-//     scanners: Vec<Box<dyn Scanner>>,
-// }
-// impl ScanMachine {
-//     /// Create a new factory
-//     ///
-//     pub fn new() -> Self {
-//         // New object
-//         let obj = Self {
-//             logger: Logger::new_for_factory(),
-//             scanners: Vec::new(),
-//         };
-//         // Info log
-//         obj.logger.info("# Scan Machine initialization");
-//         // Load builtin device producers
-//         return obj;
-//     }
-//     pub fn add_scanners(&mut self, scanners: Vec<Box<dyn Scanner>>) {
-//         for scanner in scanners {
-//             self.add_scanner(scanner);
-//         }
-//     }
-
-//     /// Add a single producer
-//     pub fn add_scanner(&mut self, scanner: Box<dyn Scanner>) {
-//         // Info log
-//         self.logger
-//             .info(format!("   - scanner - {}", scanner.name()));
-
-//         self.scanners.push(scanner);
-//     }
-
-//     ///
-//     ///
-//     ///
-//     pub fn scan(&self) -> Vec<ProductionOrder> {
-//         let mut result = Vec::new();
-//         for scanner in &self.scanners {
-//             result.extend(scanner.scan());
-//         }
-//         result
-//     }
-
-//     ///
-//     ///
-//     ///
-//     pub fn scan_as_c_string(&self) -> Result<CString, crate::Error> {
-//         let result = self.scan();
-//         let json_str =
-//             serde_json::to_string(&result).expect("Failed to serialize scan result to JSON");
-//         CString::new(json_str)
-//             .map_err(|e| crate::Error::InternalLogic(format!("Failed to build CString ({:?})", e)))
-//     }
-// }
+/// Run all the registered scanners
+///
+pub struct ScanMachine {
+    /// Local logger
+    logger: Logger,
+    /// List of known scanners
+    scanners: Vec<Box<dyn Scanner>>,
+}
+
+impl ScanMachine {
+    /// Create a new scan machine
+    ///
+    pub fn new() -> Self {
+        // New object
+        let obj = Self {
+            logger: Logger::new_for_factory(),
+            scanners: Vec::new(),
+        };
+        // Info log
+        obj.logger.info("# Scan Machine initialization");
+        return obj;
+    }
+
+    /// Add multiple scanners
+    ///
+    pub fn add_scanners(&mut self, scanners: Vec<Box<dyn Scanner>>) {
+        for scanner in scanners {
+            self.add_scanner(scanner);
+        }
+    }
+
+    /// Add a single scanner
+    ///
+    pub fn add_scanner(&mut self, scanner: Box<dyn Scanner>) {
+        // Info log
+        self.logger
+            .info(format!("   - scanner - {}", scanner.name()));
+
+        self.scanners.push(scanner);
+    }
+
+    /// True if no producer provides a scanner
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.scanners.is_empty()
+    }
+
+    /// Run all the scanners and collect the proposed production orders
+    ///
+    pub fn scan(&self) -> Vec<ProductionOrder> {
+        let mut result = Vec::new();
+        for scanner in &self.scanners {
+            let found = scanner.scan();
+            log_debug!(
+                self.logger,
+                "scanner '{}' found {} device(s)",
+                scanner.name(),
+                found.len()
+            );
+            result.extend(found);
+        }
+        result
+    }
+
+    /// Run the scan and convert the result into a c_string to send it through C interface
+    ///
+    /// ## Json Structure
+    ///
+    /// [ { "name": "...", "dref": "...", "settings": {} } ]
+    ///
+    pub fn scan_as_c_string(&self) -> Result<CString, crate::Error> {
+        let result = self.scan();
+        let json_str =
+            serde_json::to_string(&result).expect("Failed to serialize scan result to JSON");
+        CString::new(json_str)
+            .map_err(|e| crate::Error::InternalLogic(format!("Failed to build CString ({:?})", e)))
+    }
+}
@@ -1,4 +1,5 @@
-use crate::{Actions, Error, Props};
+use super::Scanner;
+use crate::{Actions, Error, Props, RebootPolicy};
 
 /// Trait to define an instance producer
 /// Its job is to produce an instance of a driver
@@ -25,4 +26,20 @@ pub trait Producer: Send {
     /// Produce a new instance of the device actions
     ///
     fn produce(&self) -> Result<Box<dyn Actions>, Error>;
+
+    /// Reboot policy of the produced instances
+    ///
+    /// A production order can override it.
+    ///
+    fn reboot_policy(&self) -> RebootPolicy {
+        RebootPolicy::default()
+    }
+
+    /// Scanner able to find the devices managed by this producer
+    ///
+    /// None if the devices cannot be discovered automatically
+    ///
+    fn scanner(&self) -> Option<Box<dyn Scanner>> {
+        None
+    }
 }
@@ -1,9 +1,10 @@
 use std::ffi::{c_char, CStr, CString};
 
+use crate::RebootPolicy;
 use serde_json::json;
 pub type InstanceSettings = serde_json::Value;
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ProductionOrder {
     /// Name of the device to be produced
     pub name: String,
@@ -13,6 +14,10 @@ pub struct ProductionOrder {
 
     ///
     pub settings: Option<InstanceSettings>,
+
+    /// Overrides the reboot policy of the producer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reboot_policy: Option<RebootPolicy>,
 }
 
 impl ProductionOrder {
@@ -23,9 +28,17 @@ impl ProductionOrder {
             name: d_name.into(),
             dref: d_ref.into(),
             settings: None,
+            reboot_policy: None,
         }
     }
 
+    /// Override the reboot policy of the producer
+    ///
+    pub fn with_reboot_policy(mut self, reboot_policy: RebootPolicy) -> Self {
+        self.reboot_policy = Some(reboot_policy);
+        self
+    }
+
     ///
     ///
     pub fn add_u16_setting<A: Into<String>>(mut self, name: A, setting: u16) -> Self {
@@ -0,0 +1,17 @@
+use crate::ProductionOrder;
+
+/// Trait to define a device scanner
+/// Its job is to find connected devices and propose production orders for them
+///
+pub trait Scanner: Send {
+    /// Name of the scanner (for logs)
+    ///
+    fn name(&self) -> String;
+
+    /// Look for connected devices
+    ///
+    /// Each returned production order must come with the settings required
+    /// to produce an instance for the found device.
+    ///
+    fn scan(&self) -> Vec<ProductionOrder>;
+}
@@ -1,8 +1,12 @@
 use crate::{Error, Props};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use serde_json::Value as JsonValue;
+use serde_json::{json, Map, Value as JsonValue};
 use std::collections::HashMap;
 
+/// Dialect of the generated JSON Schema documents
+///
+pub static JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 
 ///
@@ -20,6 +24,35 @@ pub struct Product {
     pub props: Props,
 }
 
+impl Product {
+    ///
+    /// JSON Schema of a production order for this product
+    ///
+    /// Settings are required as soon as one of the props is required.
+    ///
+    pub fn json_schema(&self, dref: &str) -> JsonValue {
+        let mut required = vec!["name", "dref"];
+        if self
+            .props
+            .iter_sorted()
+            .iter()
+            .any(|(_, prop)| prop.required)
+        {
+            required.push("settings");
+        }
+        json!({
+            "type": "object",
+            "description": self.description,
+            "properties": {
+                "name": { "type": "string" },
+                "dref": { "const": dref },
+                "settings": self.props.json_schema(),
+            },
+            "required": required,
+        })
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 /// # Store
 ///
@@ -101,4 +134,91 @@ impl Store {
     pub fn into_json_value(&self) -> Result<JsonValue, Error> {
         serde_json::to_value(&self.products).map_err(|e| Error::InternalLogic(format!("{:?}", e)))
     }
+
+    ///
+    /// Convert the store into a JSON Schema (draft 2020-12) document for tree.json files
+    ///
+    /// ## Json Structure validated
+    ///
+    /// {
+    ///     "devices": [
+    ///         { "name": "...", "dref": "...", "settings": {} }
+    ///     ]
+    /// }
+    ///
+    pub fn into_json_schema(&self) -> JsonValue {
+        //
+        // One definition per product, sorted for stable outputs
+        let mut drefs: Vec<&String> = self.products.keys().collect();
+        drefs.sort();
+        let mut defs = Map::new();
+        let mut choices = Vec::new();
+        for dref in drefs {
+            defs.insert(dref.clone(), self.products[dref].json_schema(dref));
+            choices.push(json!({ "$ref": format!("#/$defs/{}", json_pointer_escape(dref)) }));
+        }
+        defs.insert("production_order".to_string(), json!({ "oneOf": choices }));
+
+        json!({
+            "$schema": JSON_SCHEMA_DIALECT,
+            "title": "Panduza device tree",
+            "type": "object",
+            "properties": {
+                "devices": {
+                    "type": "array",
+                    "items": { "$ref": "#/$defs/production_order" },
+                },
+            },
+            "$defs": defs,
+        })
+    }
+}
+
+///
+/// Escape a key to use it inside a JSON pointer (RFC 6901)
+///
+fn json_pointer_escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Prop, PropType};
+
+    #[test]
+    fn test_store_json_schema() {
+        let mut props = Props::default();
+        props.add_ranged_number_prop("voltage", "output voltage", 5, 0.0, 30.0, "V");
+        props.add_prop(
+            "port",
+            Prop::new("serial port", PropType::String, JsonValue::Null).required(),
+        );
+
+        let mut store = Store::default();
+        store.products.insert(
+            "panduza.fake".to_string(),
+            Product {
+                description: "fake device".to_string(),
+                props: props,
+            },
+        );
+
+        let schema = store.into_json_schema();
+        assert_eq!(schema["$schema"], json!(JSON_SCHEMA_DIALECT));
+        assert_eq!(
+            schema["$defs"]["production_order"]["oneOf"][0]["$ref"],
+            json!("#/$defs/panduza.fake")
+        );
+
+        assert_eq!(
+            schema["$defs"]["panduza.fake"]["required"],
+            json!(["name", "dref", "settings"])
+        );
+        let settings = &schema["$defs"]["panduza.fake"]["properties"]["settings"];
+        assert_eq!(settings["required"], json!(["port"]));
+        assert_eq!(settings["properties"]["voltage"]["maximum"], json!(30.0));
+        assert_eq!(settings["properties"]["voltage"]["x-unit"], json!("V"));
+        assert_eq!(settings["properties"]["port"]["type"], json!("string"));
+    }
 }
@@ -3,6 +3,8 @@ pub mod attribute_builder;
 pub mod class;
 pub mod class_builder;
 pub mod container;
+pub mod element;
+pub mod reboot;
 pub mod server;
 
 use async_trait::async_trait;
@@ -10,19 +12,23 @@ use attribute_builder::AttributeServerBuilder;
 use class_builder::ClassBuilder;
 use panduza::task_monitor::{NamedTaskHandle, TaskHandle};
 use panduza::{InstanceState, TaskMonitor};
+use reboot::{RebootPolicy, RebootStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::{Mutex, Notify};
 
+use crate::engine::health::{wait_health_change, EngineHealth};
 use crate::engine::Engine;
 use crate::log_debug;
 use crate::log_error;
+use crate::log_info;
 use crate::log_trace;
 use crate::Actions;
 use crate::InstanceSettings;
 use crate::Logger;
 use crate::Notification;
-use crate::StateNotification;
 
 pub use container::Container;
 
@@ -59,6 +65,14 @@ pub struct Instance {
     ///
     state_change_notifier: Arc<Notify>,
 
+    /// How the instance reboots after an error
+    ///
+    reboot_policy: RebootPolicy,
+
+    /// Attempts and last error, reported with each state notification
+    ///
+    reboot_status: Arc<Mutex<RebootStatus>>,
+
     ///
     ///
     notification_channel: Sender<Notification>,
@@ -70,6 +84,19 @@ pub struct Instance {
     ///
     ///
     task_monitor: TaskMonitor,
+
+    /// True once a stop has been requested, the FSM will not leave
+    /// the stop path after that
+    ///
+    stop_requested: Arc<AtomicBool>,
+
+    /// Wake up the FSM when it waits for a reboot event
+    ///
+    stop_notifier: Arc<Notify>,
+
+    /// Notified when the FSM has finished its stop path
+    ///
+    stopped_notifier: Arc<Notify>,
 }
 
 impl Instance {
@@ -85,6 +112,7 @@ impl Instance {
         settings: Option<InstanceSettings>,
         notification_channel: Sender<Notification>,
         namespace: Option<String>,
+        reboot_policy: RebootPolicy,
     ) -> Instance {
         //
         // Create a task monitor for the instance
@@ -102,9 +130,14 @@ impl Instance {
             actions: Arc::new(Mutex::new(actions)),
             state: Arc::new(Mutex::new(InstanceState::Booting)),
             state_change_notifier: Arc::new(Notify::new()),
+            reboot_policy: reboot_policy,
+            reboot_status: Arc::new(Mutex::new(RebootStatus::default())),
             notification_channel: notification_channel.clone(),
             reset_signal: Arc::new(Notify::new()),
             task_monitor: task_monitor,
+            stop_requested: Arc::new(AtomicBool::new(false)),
+            stop_notifier: Arc::new(Notify::new()),
+            stopped_notifier: Arc::new(Notify::new()),
         };
 
         //
@@ -116,6 +149,9 @@ impl Instance {
             instance.state_change_notifier.clone(),
             notification_channel.clone(),
             instance.topic.clone(),
+            instance.stop_requested.clone(),
+            instance.reboot_policy.clone(),
+            instance.reboot_status.clone(),
         ));
 
         instance
@@ -152,17 +188,29 @@ impl Instance {
 
     /// Run the FSM of the device
     ///
+    /// Return once the instance has been stopped (see `request_stop`)
+    ///
     pub async fn run_fsm(&mut self) {
         //
         // First start by booting the device to give him a connection with the info_pack
         // and allow the InfoDevice to send device information on MQTT
+        *self.reboot_status.lock().await = RebootStatus::default();
         self.move_to_state(InstanceState::Booting).await;
 
+        //
+        // Follow the router connection to suspend the instance while it is lost
+        let mut engine_health = self.engine.health();
+
         //
         // Start the main loop of the device
-        // TODO => Maybe we should give a way to stop properly this task instead of canceling the task brutally
         loop {
-            self.state_change_notifier.notified().await;
+            tokio::select! {
+                _ = self.state_change_notifier.notified() => {}
+                health = wait_health_change(&mut engine_health) => {
+                    self.process_engine_health(health).await;
+                    continue;
+                }
+            }
 
             // Helper log
             let stateee = self.state.lock().await.clone();
@@ -180,7 +228,7 @@ impl Instance {
                     // }
                     self.move_to_state(InstanceState::Initializating).await;
                 }
-                InstanceState::Connecting => {} // wait for reactor signal
+                InstanceState::Connecting => {} // wait for the router to come back
                 InstanceState::Initializating => {
                     //
                     // Try to mount the device
@@ -190,10 +238,16 @@ impl Instance {
                     match mount_result {
                         Ok(_) => {
                             self.logger.debug("FSM Mount Success ");
+                            self.reboot_status
+                                .lock()
+                                .await
+                                .start_running(Instant::now());
                             self.move_to_state(InstanceState::Running).await;
                         }
                         Err(e) => {
                             log_error!(self.logger, "Instance Mount Failure '{:?}'", e);
+                            self.reboot_status.lock().await.last_error =
+                                Some(format!("Mount failure ({:?})", e));
                             self.move_to_state(InstanceState::Error).await;
                         }
                     }
@@ -202,23 +256,135 @@ impl Instance {
                 InstanceState::Error => {
                     self.task_monitor.cancel_all_monitored_tasks().await;
                     //
-                    // Wait before reboot
-                    self.actions
-                        .lock()
-                        .await
-                        .wait_reboot_event(self.clone())
-                        .await;
-                    self.logger.info("try to reboot");
-                    self.move_to_state(InstanceState::Initializating).await;
+                    // The attempts count again from zero only after a stable run
+                    self.reboot_status.lock().await.end_running(Instant::now());
+                    //
+                    // Stay in Error once the policy has no attempt left, until a stop request
+                    let attempt = self.reboot_status.lock().await.attempts + 1;
+                    if !self.reboot_policy.allows(attempt) {
+                        log_error!(
+                            self.logger,
+                            "Reboot given up after {} attempts",
+                            attempt - 1
+                        );
+                        self.reboot_status.lock().await.given_up = true;
+                        self.notify_state(InstanceState::Error).await;
+                        self.wait_stop_request().await;
+                        continue;
+                    }
+                    //
+                    // Wait before reboot, a stop request interrupts the wait
+                    let instance = self.clone();
+                    let mut actions = self.actions.lock().await;
+                    let delay = self.reboot_policy.delay_for(attempt);
+                    tokio::select! {
+                        _ = async {
+                            actions.wait_reboot_event(instance).await;
+                            tokio::time::sleep(delay).await;
+                        } => {
+                            drop(actions);
+                            log_info!(self.logger, "try to reboot (attempt {})", attempt);
+                            self.reboot_status.lock().await.attempts = attempt;
+                            self.move_to_state(InstanceState::Initializating).await;
+                        }
+                        _ = self.wait_stop_request() => {}
+                    }
                 }
                 InstanceState::Warning => {}
-                InstanceState::Cleaning => {}
-                InstanceState::Stopping => {}
+                InstanceState::Stopping => {
+                    //
+                    // Stop everything that runs for the instance
+                    self.task_monitor.cancel_all_monitored_tasks().await;
+                    //
+                    // Let the driver release its resources
+                    let unmount_result = self.actions.lock().await.unmount(self.clone()).await;
+                    if let Err(e) = unmount_result {
+                        log_error!(self.logger, "Instance Unmount Failure '{:?}'", e);
+                    }
+                    self.move_to_state(InstanceState::Cleaning).await;
+                }
+                InstanceState::Cleaning => {
+                    self.logger.info("instance stopped");
+                    self.stopped_notifier.notify_waiters();
+                    break;
+                }
                 InstanceState::Undefined => {}
             }
         }
+    }
+
+    /// Suspend the instance while the router is lost and remount it once restored
+    ///
+    /// Remounting declares again all the publishers, subscribers and queryables
+    /// of the instance on the session.
+    ///
+    async fn process_engine_health(&mut self, health: EngineHealth) {
+        let state = self.state.lock().await.clone();
+        match health {
+            EngineHealth::Lost => {
+                if matches!(
+                    state,
+                    InstanceState::Initializating | InstanceState::Running | InstanceState::Warning
+                ) {
+                    //
+                    // Connecting first, the task monitor ignores the failures of the
+                    // cancelled tasks only in this state
+                    self.move_to_state(InstanceState::Connecting).await;
+                    self.task_monitor.cancel_all_monitored_tasks().await;
+                    self.reboot_status.lock().await.end_running(Instant::now());
+                }
+            }
+            EngineHealth::Connected => {
+                if matches!(state, InstanceState::Connecting) {
+                    self.move_to_state(InstanceState::Initializating).await;
+                }
+            }
+        }
+    }
+
+    /// Request the instance to stop
+    ///
+    /// The FSM unmounts the instance, sends a final `Cleaning` state notification
+    /// and `run_fsm` returns.
+    ///
+    pub async fn request_stop(&mut self) {
+        self.stop_requested.store(true, Ordering::Relaxed);
+        self.stop_notifier.notify_waiters();
+        self.move_to_state(InstanceState::Stopping).await;
+    }
+
+    /// True if a stop has been requested for this instance
+    ///
+    pub fn is_stop_requested(&self) -> bool {
+        self.stop_requested.load(Ordering::Relaxed)
+    }
+
+    /// Wait until the FSM has finished its stop path
+    ///
+    pub async fn wait_stopped(&self) {
+        loop {
+            let notified = self.stopped_notifier.notified();
+            if matches!(*self.state.lock().await, InstanceState::Cleaning) {
+                return;
+            }
+            notified.await;
+        }
+    }
 
-        // Ok(())
+    /// Allow a stopped instance to run its FSM again
+    ///
+    pub(crate) fn clear_stop_request(&self) {
+        self.stop_requested.store(false, Ordering::Relaxed);
+    }
+
+    /// Wait until a stop is requested
+    ///
+    async fn wait_stop_request(&self) {
+        let notified = self.stop_notifier.notified();
+        if self.is_stop_requested() {
+            return;
+        }
+        notified.await;
     }
 
     /// Clone settings of the device
@@ -243,21 +409,36 @@ impl Instance {
     /// Function to change the current state of the device FSM
     ///
     pub async fn move_to_state(&mut self, new_state: InstanceState) {
+        // Once stopping, only the stop path is allowed
+        if self.is_stop_requested()
+            && !matches!(new_state, InstanceState::Stopping | InstanceState::Cleaning)
+        {
+            log_trace!(self.logger, "Ignore state {} during stop", new_state);
+            return;
+        }
+
         // Set the new state
         *self.state.lock().await = new_state.clone();
 
         // Alert monitoring device "_"
-        if let Err(err) = self
-            .notification_channel
-            .send(StateNotification::new(self.topic.clone(), new_state.clone()).into())
-            .await
-        {
-            log_error!(self.logger, "Failed to send state notification: {}", err);
-        }
+        self.notify_state(new_state).await;
 
         // Notify FSM
         self.state_change_notifier.notify_one();
     }
+
+    /// Send the state notification with the reboot status
+    ///
+    async fn notify_state(&self, state: InstanceState) {
+        let notification = self.reboot_status.lock().await.state_notification(
+            self.topic.clone(),
+            state,
+            &self.reboot_policy,
+        );
+        if let Err(err) = self.notification_channel.send(notification.into()).await {
+            log_error!(self.logger, "Failed to send state notification: {}", err);
+        }
+    }
 }
 
 #[async_trait]
@@ -297,7 +478,7 @@ impl Container for Instance {
     fn create_attribute<N: Into<String>>(&mut self, name: N) -> AttributeServerBuilder {
         AttributeServerBuilder::new(
             self.engine.clone(),
-            // None,
+            None,
             self.notification_channel.clone(),
             self.task_monitor_sender().clone(),
         )
@@ -323,6 +504,9 @@ async fn handle_task_monitor_events(
     state_change_notifier: Arc<Notify>,
     notification_channel: Sender<Notification>,
     topic: String,
+    stop_requested: Arc<AtomicBool>,
+    reboot_policy: RebootPolicy,
+    reboot_status: Arc<Mutex<RebootStatus>>,
 ) {
     loop {
         let event_recv = event_receiver.recv().await;
@@ -357,16 +541,38 @@ async fn handle_task_monitor_events(
                                 .unwrap_or_else(|| "No error details".into())
                         );
 
+                        // Tasks are cancelled on purpose during a stop
+                        if stop_requested.load(Ordering::Relaxed) {
+                            continue;
+                        }
+
+                        // Same while the instance waits for the router
+                        if matches!(*state.lock().await, InstanceState::Connecting) {
+                            continue;
+                        }
+
                         // Mettre à jour l'état
                         *state.lock().await = InstanceState::Error;
 
                         // Envoyer la notification
-                        if let Err(err) = notification_channel
-                            .send(
-                                StateNotification::new(topic.clone(), InstanceState::Error).into(),
+                        let notification = {
+                            let mut status = reboot_status.lock().await;
+                            status.last_error = Some(format!(
+                                "{} on task {} - {}",
+                                error_type,
+                                event_body.task_name,
+                                event_body
+                                    .error_message
+                                    .clone()
+                                    .unwrap_or_else(|| "No error details".into())
+                            ));
+                            status.state_notification(
+                                topic.clone(),
+                                InstanceState::Error,
+                                &reboot_policy,
                             )
-                            .await
-                        {
+                        };
+                        if let Err(err) = notification_channel.send(notification.into()).await {
                             log_error!(logger, "Failed to send notification: {}", err);
                         }
 
@@ -16,4 +16,13 @@ pub trait Actions: Send + Sync {
     /// Once this function return, the instance will reboot
     ///
     async fn wait_reboot_event(&mut self, mut instance: Instance);
+
+    /// Unmount instance
+    ///
+    /// Called when the instance is requested to stop, after all the monitored
+    /// tasks have been cancelled. Use it to release ports and connections.
+    ///
+    async fn unmount(&mut self, _instance: Instance) -> Result<(), Error> {
+        Ok(())
+    }
 }
@@ -1,12 +1,18 @@
+use super::element::{Element, Enablement};
 use super::server::boolean::BooleanAttributeServer;
 use super::server::bytes::BytesAttributeServer;
 use super::server::json::JsonAttributeServer;
 use super::server::notification::NotificationAttributeServer;
-use super::server::number::NumberAttributeServer;
+use super::server::number::{NumberAttributeServer, NumberSettings};
+use super::server::r#enum::EnumAttributeServer;
 use super::server::status::StatusAttributeServer;
 use super::server::string::StringAttributeServer;
+use super::server::vector::{
+    VectorAttributeServer, VectorF32AttributeServer, VectorF64AttributeServer, VectorMetadata,
+    VectorSample,
+};
+use crate::instance::class::Class;
 use crate::instance::server::structure::StructureAttributeServer;
-// use crate::instance::class::Class;
 use crate::runtime::notification::attribute::AttributeMode;
 use crate::AttributeNotification;
 use crate::Engine;
@@ -30,7 +36,7 @@ pub struct AttributeServerBuilder {
 
     /// Parent class if any
     ///
-    // parent_class: Option<Class>,
+    parent_class: Option<Class>,
 
     /// Topic of the attribute
     pub topic: Option<String>,
@@ -45,6 +51,10 @@ pub struct AttributeServerBuilder {
 
     pub info: Option<String>,
 
+    /// Constraints of a number attribute
+    ///
+    pub number_settings: NumberSettings,
+
     /// Channel to send notifications
     ///
     notification_channel: Sender<Notification>,
@@ -59,18 +69,19 @@ impl AttributeServerBuilder {
     ///
     pub fn new(
         engine: Engine,
-        // parent_class: Option<Class>,
+        parent_class: Option<Class>,
         notification_channel: Sender<Notification>,
         task_monitor_sender: Sender<NamedTaskHandle>,
     ) -> Self {
         Self {
             engine,
-            // parent_class,
+            parent_class,
             topic: None,
             settings: None,
             mode: Some(AttributeMode::ReadOnly),
             r#type: None,
             info: None,
+            number_settings: NumberSettings::default(),
             notification_channel: notification_channel,
             task_monitor_sender: task_monitor_sender,
         }
@@ -118,6 +129,35 @@ impl AttributeServerBuilder {
         self
     }
 
+    /// Accept only values between min and max (number only)
+    ///
+    pub fn with_range(mut self, min: f64, max: f64) -> Self {
+        self.number_settings.min = Some(min);
+        self.number_settings.max = Some(max);
+        self
+    }
+
+    /// Accept only those values (number only)
+    ///
+    pub fn with_whitelist(mut self, whitelist: Vec<f64>) -> Self {
+        self.number_settings.whitelist = Some(whitelist);
+        self
+    }
+
+    /// Unit of the value (number only)
+    ///
+    pub fn with_unit<U: Into<String>>(mut self, unit: U) -> Self {
+        self.number_settings.unit = Some(unit.into());
+        self
+    }
+
+    /// Round the values to this number of decimals (number only)
+    ///
+    pub fn with_decimals(mut self, decimals: usize) -> Self {
+        self.number_settings.decimals = Some(decimals);
+        self
+    }
+
     // ------------------------------------------------------------------------
 
     /// Send a notification to the platform
@@ -148,6 +188,20 @@ impl AttributeServerBuilder {
             .unwrap();
     }
 
+    /// Attach the attribute to its parent class, it follows the class enablement
+    ///
+    async fn attach_to_parent(&self, enablement: Enablement) -> Result<(), Error> {
+        if let Some(parent_class) = &self.parent_class {
+            parent_class
+                .push_sub_element(Element::Attribute(enablement.clone()))
+                .await;
+            if !parent_class.is_enabled() {
+                enablement.change_inherited(false).await?;
+            }
+        }
+        Ok(())
+    }
+
     // ------------------------------------------------------------------------
 
     ///
@@ -213,12 +267,14 @@ impl AttributeServerBuilder {
         self.r#type = Some("boolean".to_string());
         self.send_creation_notification().await;
         let att = BooleanAttributeServer::new(
-            self.engine.session,
-            self.topic.unwrap(),
-            self.task_monitor_sender,
-            self.notification_channel,
+            self.engine.session.clone(),
+            self.topic.clone().unwrap(),
+            self.mode.clone().unwrap(),
+            self.task_monitor_sender.clone(),
+            self.notification_channel.clone(),
         )
         .await;
+        self.attach_to_parent(att.enablement()).await?;
         Ok(att)
     }
 
@@ -228,14 +284,18 @@ impl AttributeServerBuilder {
     ///
     pub async fn start_as_number(mut self) -> Result<NumberAttributeServer, Error> {
         self.r#type = Some("number".to_string());
+        self.settings = self.number_settings.merge_into(self.settings.take());
         self.send_creation_notification().await;
         let att = NumberAttributeServer::new(
-            self.engine.session,
-            self.topic.unwrap(),
-            self.task_monitor_sender,
-            self.notification_channel,
+            self.engine.session.clone(),
+            self.topic.clone().unwrap(),
+            self.mode.clone().unwrap(),
+            self.number_settings.clone(),
+            self.task_monitor_sender.clone(),
+            self.notification_channel.clone(),
         )
         .await;
+        self.attach_to_parent(att.enablement()).await?;
         Ok(att)
     }
 
@@ -247,12 +307,53 @@ impl AttributeServerBuilder {
         self.r#type = Some("string".to_string());
         self.send_creation_notification().await;
         let att = StringAttributeServer::new(
-            self.engine.session,
-            self.topic.unwrap(),
-            self.task_monitor_sender,
-            self.notification_channel,
+            self.engine.session.clone(),
+            self.topic.clone().unwrap(),
+            self.mode.clone().unwrap(),
+            self.task_monitor_sender.clone(),
+            self.notification_channel.clone(),
+        )
+        .await;
+        self.attach_to_parent(att.enablement()).await?;
+        Ok(att)
+    }
+
+    // ------------------------------------------------------------------------
+
+    /// ENUM
+    ///
+    /// The choices are published in the settings of the attribute, the first one
+    /// is the value until the driver sets it. Error::InvalidArgument without choices.
+    ///
+    pub async fn start_as_enum<S: Into<String>>(
+        mut self,
+        choices: Vec<S>,
+    ) -> Result<EnumAttributeServer, Error> {
+        let choices: Vec<String> = choices.into_iter().map(|c| c.into()).collect();
+        if choices.is_empty() {
+            return Err(Error::InvalidArgument(format!(
+                "Enum attribute '{}' requires at least one choice",
+                self.topic.clone().unwrap_or_default()
+            )));
+        }
+        self.r#type = Some(EnumAttributeServer::r#type());
+        let mut settings = match self.settings.take() {
+            Some(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        settings.insert("choices".to_string(), serde_json::json!(choices));
+        self.settings = Some(serde_json::Value::Object(settings));
+        self.send_creation_notification().await;
+        let att = EnumAttributeServer::new(
+            self.engine.session.clone(),
+            self.topic.clone().unwrap(),
+            self.mode.clone().unwrap(),
+            choices,
+            self.task_monitor_sender.clone(),
+            self.notification_channel.clone(),
         )
         .await;
+        self.attach_to_parent(att.enablement()).await?;
         Ok(att)
     }
 
@@ -264,17 +365,64 @@ impl AttributeServerBuilder {
         self.r#type = Some("bytes".to_string());
         self.send_creation_notification().await;
         let att = BytesAttributeServer::new(
-            self.engine.session,
-            self.topic.unwrap(),
-            self.task_monitor_sender,
-            self.notification_channel,
+            self.engine.session.clone(),
+            self.topic.clone().unwrap(),
+            self.mode.clone().unwrap(),
+            self.task_monitor_sender.clone(),
+            self.notification_channel.clone(),
         )
         .await;
+        self.attach_to_parent(att.enablement()).await?;
         Ok(att)
     }
 
     // ------------------------------------------------------------------------
 
+    /// VECTOR
+    ///
+    /// The initial metadata are published in the settings of the attribute,
+    /// then each acquisition carries its own metadata.
+    ///
+    pub async fn start_as_vector<T: VectorSample>(
+        mut self,
+        metadata: VectorMetadata,
+    ) -> Result<VectorAttributeServer<T>, Error> {
+        self.r#type = Some(VectorAttributeServer::<T>::r#type());
+        let mut settings = match self.settings.take() {
+            Some(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        settings.insert("sample_type".to_string(), serde_json::json!(T::NAME));
+        settings.insert("metadata".to_string(), serde_json::json!(metadata));
+        self.settings = Some(serde_json::Value::Object(settings));
+        self.send_creation_notification().await;
+        let att = VectorAttributeServer::<T>::new(
+            self.engine.session.clone(),
+            self.topic.clone().unwrap(),
+            self.mode.clone().unwrap(),
+            metadata,
+            self.task_monitor_sender.clone(),
+            self.notification_channel.clone(),
+        )
+        .await;
+        self.attach_to_parent(att.enablement()).await?;
+        Ok(att)
+    }
+
+    /// VECTOR F32
+    ///
+    pub async fn start_as_vector_f32(self) -> Result<VectorF32AttributeServer, Error> {
+        self.start_as_vector::<f32>(VectorMetadata::default()).await
+    }
+
+    /// VECTOR F64
+    ///
+    pub async fn start_as_vector_f64(self) -> Result<VectorF64AttributeServer, Error> {
+        self.start_as_vector::<f64>(VectorMetadata::default()).await
+    }
+
+    // ------------------------------------------------------------------------
+
     /// NOTIFICATION
     ///
     pub async fn __start_as_notification(mut self) -> Result<NotificationAttributeServer, Error> {
@@ -298,6 +446,7 @@ impl AttributeServerBuilder {
         let att = StatusAttributeServer::new(
             self.engine.session,
             self.topic.unwrap(),
+            self.mode.unwrap(),
             self.task_monitor_sender,
             self.notification_channel,
         )
@@ -313,6 +462,7 @@ impl AttributeServerBuilder {
         let att = StructureAttributeServer::new(
             self.engine.session,
             self.topic.unwrap(),
+            self.mode.unwrap(),
             self.task_monitor_sender,
             self.notification_channel,
         )
@@ -329,11 +479,13 @@ impl AttributeServerBuilder {
         let att = JsonAttributeServer::new(
             self.engine.session.clone(),
             topic.clone(),
+            self.mode.clone().unwrap(),
             cmd_receiver,
             self.task_monitor_sender.clone(),
             self.notification_channel.clone(),
         )
         .await;
+        self.attach_to_parent(att.enablement()).await?;
         Ok(att)
     }
 }
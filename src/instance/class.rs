@@ -1,12 +1,12 @@
 use super::attribute_builder::AttributeServerBuilder;
+use super::element::{Element, Enablement};
 use super::{class_builder::ClassBuilder, Container};
 use crate::{Error, Instance, Logger, Notification};
 use async_trait::async_trait;
 use panduza::task_monitor::{NamedTaskHandle, TaskHandle};
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
-use tokio::sync::Notify;
+use tokio::sync::{Mutex, Notify};
 
 #[derive(Clone)]
 ///
@@ -24,16 +24,18 @@ pub struct Class {
     ///
     topic: String,
 
+    /// Enablement of the class
     ///
-    ///
-    enabled: Arc<AtomicBool>,
+    enablement: Enablement,
 
     ///
     ///
     notification_channel: Sender<Notification>,
-    // / Sub elements
-    // /
-    // sub_elements: Arc<Mutex<Vec<Element>>>,
+
+    /// Sub elements
+    ///
+    sub_elements: Arc<Mutex<Vec<Element>>>,
+
     ///
     ///
     reset_signal: Arc<Notify>,
@@ -49,40 +51,78 @@ impl Class {
             logger: builder.instance.logger.new_for_class(&builder.topic),
             instance: builder.instance.clone(),
             topic: builder.topic.clone(),
-            enabled: Arc::new(AtomicBool::new(true)),
-            notification_channel: notification_channel, // sub_elements: Arc::new(Mutex::new(Vec::new())),
+            enablement: Enablement::new(builder.topic.clone(), notification_channel.clone()),
+            notification_channel: notification_channel,
+            sub_elements: Arc::new(Mutex::new(Vec::new())),
             reset_signal: builder.instance.reset_signal(),
             monitor_task_send: builder.instance.task_monitor_sender().clone(),
         }
     }
 
-    // /// Clone as an element object
-    // ///
-    // pub fn clone_as_element(&self) -> Element {
-    //     Element::Class(self.clone())
-    // }
+    /// Topic of the class
+    ///
+    pub fn topic(&self) -> &String {
+        &self.topic
+    }
 
-    // /// Append a new sub element
-    // ///
-    // pub async fn push_sub_element(&mut self, element: Element) {
-    //     self.sub_elements.lock().await.push(element);
-    // }
+    /// Clone as an element object
+    ///
+    pub fn clone_as_element(&self) -> Element {
+        Element::Class(self.clone())
+    }
 
-    pub async fn change_enablement(&mut self, enabled: bool) -> Result<(), Error> {
+    /// Append a new sub element
+    ///
+    pub async fn push_sub_element(&self, element: Element) {
+        self.sub_elements.lock().await.push(element);
+    }
+
+    /// True if the class is enabled
+    ///
+    pub fn is_enabled(&self) -> bool {
+        self.enablement.is_enabled()
+    }
+
+    /// Enable or disable the class with all its sub classes and attributes
+    ///
+    /// Sub elements disabled on their own stay disabled when the class is enabled.
+    ///
+    pub async fn change_enablement(&self, enabled: bool) -> Result<(), Error> {
         //
         // Flag local variable
-        self.enabled.store(enabled, Ordering::Relaxed);
+        self.enablement.change(enabled).await?;
+        self.propagate_enablement().await
+    }
 
-        //
-        // Also change sub elements
-        // let mut lock = self.sub_elements.lock().await;
-        // for i in 0..lock.len() {
-        //     if let Some(obj) = lock.get_mut(i) {
-        //         obj.change_enablement(enabled).await?;
-        //     }
-        // }
+    /// Give the state of the parent class to this class and its sub elements
+    ///
+    pub async fn change_inherited_enablement(&self, enabled: bool) -> Result<(), Error> {
+        self.enablement.change_inherited(enabled).await?;
+        self.propagate_enablement().await
+    }
+
+    /// Give the state of the class to its sub elements
+    ///
+    async fn propagate_enablement(&self) -> Result<(), Error> {
+        let enabled = self.is_enabled();
+        let sub_elements = self.sub_elements.lock().await.clone();
+        for element in sub_elements.iter() {
+            element.change_inherited_enablement(enabled).await?;
+        }
         Ok(())
     }
+
+    /// Enable the class and its sub elements
+    ///
+    pub async fn enable(&self) -> Result<(), Error> {
+        self.change_enablement(true).await
+    }
+
+    /// Disable the class and its sub elements
+    ///
+    pub async fn disable(&self) -> Result<(), Error> {
+        self.change_enablement(false).await
+    }
 }
 
 #[async_trait]
@@ -121,7 +161,7 @@ impl Container for Class {
     fn create_attribute<N: Into<String>>(&mut self, name: N) -> AttributeServerBuilder {
         AttributeServerBuilder::new(
             self.instance.engine.clone(),
-            // None,
+            Some(self.clone()),
             self.notification_channel.clone(),
             self.monitor_task_send.clone(),
         )
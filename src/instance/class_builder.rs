@@ -1,6 +1,6 @@
 use tokio::sync::mpsc::Sender;
 
-use crate::{ClassNotification, Notification};
+use crate::{ClassNotification, Error, Notification};
 
 use super::{class::Class, Instance};
 
@@ -52,10 +52,11 @@ impl ClassBuilder {
         self
     }
 
+    /// Create the class and attach it to its parent class
     ///
+    /// A class created under a disabled parent starts disabled.
     ///
-    ///
-    pub async fn finish(self) -> Class {
+    pub async fn finish(self) -> Result<Class, Error> {
         let bis = self.topic.clone();
 
         //
@@ -63,19 +64,22 @@ impl ClassBuilder {
         self.notification_channel
             .send(ClassNotification::new(bis, self.tags.clone()).into())
             .await
-            .unwrap();
+            .map_err(|e| Error::InternalLogic(format!("Notification channel closed ({:?})", e)))?;
 
         //
         let class = Class::new(&self, self.notification_channel.clone());
 
         //
-        // Attach the attribute to its parent class if exist
-        // if let Some(mut parent_class) = self.parent_class {
-        //     parent_class
-        //         .push_sub_element(class.clone_as_element())
-        //         .await;
-        // }
+        // Attach the class to its parent class if exist
+        if let Some(parent_class) = &self.parent_class {
+            parent_class
+                .push_sub_element(class.clone_as_element())
+                .await;
+            if !parent_class.is_enabled() {
+                class.change_inherited_enablement(false).await?;
+            }
+        }
 
-        class
+        Ok(class)
     }
 }
@@ -1,32 +1,163 @@
-use crate::{BooleanAttributeServer, Class, Error, JsonAttServer};
+use crate::{Class, EnablementNotification, Error, Notification};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::Sender;
 
 #[derive(Clone)]
+/// Enablement flag of a class or an attribute, shared with its tasks
+///
+/// The element is enabled when both its own flag and the one inherited from its
+/// parent are set, so re-enabling a parent does not re-enable a child that has
+/// been disabled on its own. Each change of the resulting state is reported to
+/// the platform with an EnablementNotification.
+///
+pub struct Enablement {
+    /// Topic of the class or the attribute
+    ///
+    topic: String,
+
+    /// Own flag and flag inherited from the parent
+    ///
+    flags: Arc<Mutex<(bool, bool)>>,
+
+    /// Resulting state
+    ///
+    enabled: Arc<AtomicBool>,
+
+    /// Channel to send notifications
+    ///
+    notification_channel: Sender<Notification>,
+}
+
+impl Enablement {
+    /// Create an enabled flag
+    ///
+    pub fn new<A: Into<String>>(topic: A, notification_channel: Sender<Notification>) -> Self {
+        Self {
+            topic: topic.into(),
+            flags: Arc::new(Mutex::new((true, true))),
+            enabled: Arc::new(AtomicBool::new(true)),
+            notification_channel: notification_channel,
+        }
+    }
+
+    /// Topic of the class or the attribute
+    ///
+    pub fn topic(&self) -> &String {
+        &self.topic
+    }
+
+    /// True if enabled
+    ///
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Change the own flag and notify the platform if the state changed
+    ///
+    pub async fn change(&self, enabled: bool) -> Result<(), Error> {
+        self.update(|flags| flags.0 = enabled).await
+    }
+
+    /// Change the flag inherited from the parent and notify the platform if the state changed
+    ///
+    pub async fn change_inherited(&self, enabled: bool) -> Result<(), Error> {
+        self.update(|flags| flags.1 = enabled).await
+    }
+
+    /// Apply a change on the flags and notify the new state if it changed
+    ///
+    async fn update<F: FnOnce(&mut (bool, bool))>(&self, change: F) -> Result<(), Error> {
+        let enabled = {
+            let mut flags = self.flags.lock().unwrap();
+            change(&mut flags);
+            let enabled = flags.0 && flags.1;
+            if self.enabled.swap(enabled, Ordering::Relaxed) == enabled {
+                return Ok(());
+            }
+            enabled
+        };
+        self.notification_channel
+            .send(EnablementNotification::new(self.topic.clone(), enabled).into())
+            .await
+            .map_err(|e| Error::InternalLogic(format!("Notification channel closed ({:?})", e)))
+    }
+}
+
+#[derive(Clone)]
+/// Child of a class
+///
 pub enum Element {
     Class(Class),
-    AsBoolean(BooleanAttributeServer),
-    AsJson(JsonAttServer),
+    Attribute(Enablement),
 }
 
 impl Element {
-    /// Request attribute server enablement
+    /// Request element enablement, the children of a class follow
     ///
-    pub async fn change_enablement(&mut self, enabled: bool) -> Result<(), Error> {
+    pub fn change_enablement(
+        &self,
+        enabled: bool,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send + '_>> {
         match self {
-            Element::Class(_class) => Ok(()),
-            Element::AsBoolean(att_server) => att_server.change_enablement(enabled).await,
-            Element::AsJson(att_server) => att_server.change_enablement(enabled).await,
+            Element::Class(class) => Box::pin(class.change_enablement(enabled)),
+            Element::Attribute(enablement) => Box::pin(enablement.change(enabled)),
         }
     }
 
-    /// Request attribute server enablement
+    /// Give the state of the parent to the element
     ///
-    pub async fn enable(&mut self) -> Result<(), Error> {
+    pub fn change_inherited_enablement(
+        &self,
+        enabled: bool,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send + '_>> {
+        match self {
+            Element::Class(class) => Box::pin(class.change_inherited_enablement(enabled)),
+            Element::Attribute(enablement) => Box::pin(enablement.change_inherited(enabled)),
+        }
+    }
+
+    /// Request element enablement
+    ///
+    pub async fn enable(&self) -> Result<(), Error> {
         self.change_enablement(true).await
     }
 
-    /// Request attribute server disablement
+    /// Request element disablement
     ///
-    pub async fn disable(&mut self) -> Result<(), Error> {
+    pub async fn disable(&self) -> Result<(), Error> {
         self.change_enablement(false).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc::channel;
+
+    #[tokio::test]
+    async fn test_enablement_keeps_own_flag() {
+        let (sender, mut receiver) = channel(8);
+        let enablement = Enablement::new("dev/class/att", sender);
+
+        //
+        // Disabled on its own, then its parent is disabled and enabled again
+        enablement.change(false).await.unwrap();
+        enablement.change_inherited(false).await.unwrap();
+        enablement.change_inherited(true).await.unwrap();
+        assert!(!enablement.is_enabled());
+
+        enablement.change(true).await.unwrap();
+        assert!(enablement.is_enabled());
+
+        //
+        // Only the changes of the resulting state are notified
+        let mut count = 0;
+        while receiver.try_recv().is_ok() {
+            count += 1;
+        }
+        assert_eq!(count, 2);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::StateNotification;
+use panduza::InstanceState;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Running period after which an instance is considered stable again
+///
+/// The reboot attempts are counted again from zero after such a period.
+///
+pub const STABLE_RUNNING_PERIOD: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+/// How long an instance in error waits before each reboot
+///
+/// The wait starts once `Actions::wait_reboot_event` has returned.
+///
+pub enum RebootStrategy {
+    /// Reboot as soon as the reboot event is received
+    ///
+    Immediate,
+
+    /// Always wait the same delay
+    ///
+    Fixed { delay_ms: u64 },
+
+    /// Double the delay after each failed attempt, up to 'max_delay_ms'
+    ///
+    Exponential {
+        initial_delay_ms: u64,
+        max_delay_ms: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Reboot policy of an instance
+///
+/// Given by the producer, a production order can override it.
+///
+/// ## Json Structure
+///
+/// { "strategy": "exponential", "initial_delay_ms": 500, "max_delay_ms": 30000, "max_attempts": 5 }
+///
+pub struct RebootPolicy {
+    /// Delay before each reboot
+    ///
+    #[serde(flatten)]
+    pub strategy: RebootStrategy,
+
+    /// Consecutive reboots allowed before giving up, None for no limit
+    ///
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for RebootPolicy {
+    /// Reboot immediately, forever
+    ///
+    fn default() -> Self {
+        Self::immediate()
+    }
+}
+
+impl RebootPolicy {
+    /// Reboot as soon as the reboot event is received
+    ///
+    pub fn immediate() -> Self {
+        Self {
+            strategy: RebootStrategy::Immediate,
+            max_attempts: None,
+        }
+    }
+
+    /// Wait the same delay before each reboot
+    ///
+    pub fn fixed(delay: Duration) -> Self {
+        Self {
+            strategy: RebootStrategy::Fixed {
+                delay_ms: delay.as_millis() as u64,
+            },
+            max_attempts: None,
+        }
+    }
+
+    /// Double the delay after each failed attempt
+    ///
+    pub fn exponential(initial_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            strategy: RebootStrategy::Exponential {
+                initial_delay_ms: initial_delay.as_millis() as u64,
+                max_delay_ms: max_delay.as_millis() as u64,
+            },
+            max_attempts: None,
+        }
+    }
+
+    /// Give up after this number of consecutive reboots
+    ///
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// True if the policy allows this attempt (the first reboot is the attempt 1)
+    ///
+    pub fn allows(&self, attempt: u32) -> bool {
+        match self.max_attempts {
+            Some(max) => attempt <= max,
+            None => true,
+        }
+    }
+
+    /// Delay to wait before this attempt
+    ///
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        match &self.strategy {
+            RebootStrategy::Immediate => Duration::ZERO,
+            RebootStrategy::Fixed { delay_ms } => Duration::from_millis(*delay_ms),
+            RebootStrategy::Exponential {
+                initial_delay_ms,
+                max_delay_ms,
+            } => {
+                let shift = attempt.saturating_sub(1).min(63);
+                let delay_ms = initial_delay_ms
+                    .checked_mul(1u64 << shift)
+                    .unwrap_or(u64::MAX)
+                    .min(*max_delay_ms);
+                Duration::from_millis(delay_ms)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Reboot status of an instance, reported with each state notification
+///
+pub struct RebootStatus {
+    /// Consecutive reboots since the last stable run (see STABLE_RUNNING_PERIOD)
+    ///
+    pub attempts: u32,
+
+    /// Message of the last error of the instance
+    ///
+    pub last_error: Option<String>,
+
+    /// True once the policy has no attempt left
+    ///
+    pub given_up: bool,
+
+    /// Start of the current Running period
+    ///
+    pub running_since: Option<Instant>,
+}
+
+impl RebootStatus {
+    /// Record the start of a Running period
+    ///
+    pub fn start_running(&mut self, now: Instant) {
+        self.running_since = Some(now);
+    }
+
+    /// Record the end of the Running period
+    ///
+    /// A mount that does not stay up does not reset the attempts, else the
+    /// backoff and the max attempts of the policy would never apply.
+    ///
+    pub fn end_running(&mut self, now: Instant) {
+        if let Some(since) = self.running_since.take() {
+            if now.saturating_duration_since(since) >= STABLE_RUNNING_PERIOD {
+                self.attempts = 0;
+            }
+        }
+    }
+
+    /// Build the state notification of the instance
+    ///
+    pub fn state_notification(
+        &self,
+        topic: String,
+        state: InstanceState,
+        policy: &RebootPolicy,
+    ) -> StateNotification {
+        let mut notification = StateNotification::new(topic, state);
+        notification.attempts = self.attempts;
+        notification.max_attempts = policy.max_attempts;
+        notification.last_error = self.last_error.clone();
+        notification.given_up = self.given_up;
+        notification
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reboot_policy() {
+        let policy = RebootPolicy::exponential(Duration::from_millis(100), Duration::from_secs(1))
+            .with_max_attempts(5);
+        assert_eq!(policy.delay_for(1), Duration::from_millis(100));
+        assert_eq!(policy.delay_for(3), Duration::from_millis(400));
+        assert_eq!(policy.delay_for(5), Duration::from_secs(1));
+        assert_eq!(policy.delay_for(200), Duration::from_secs(1));
+        assert!(policy.allows(5));
+        assert!(!policy.allows(6));
+        assert!(RebootPolicy::default().allows(u32::MAX));
+
+        let json = serde_json::json!({
+            "strategy": "fixed",
+            "delay_ms": 250,
+            "max_attempts": 3
+        });
+        let policy: RebootPolicy = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            policy,
+            RebootPolicy::fixed(Duration::from_millis(250)).with_max_attempts(3)
+        );
+        assert_eq!(policy.delay_for(10), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_reboot_status_stable_run() {
+        let start = Instant::now();
+        let mut status = RebootStatus {
+            attempts: 3,
+            ..Default::default()
+        };
+
+        //
+        // A short run keeps the attempts
+        status.start_running(start);
+        status.end_running(start + Duration::from_secs(1));
+        assert_eq!(status.attempts, 3);
+
+        //
+        // Only the end of a Running period counts
+        status.end_running(start + STABLE_RUNNING_PERIOD);
+        assert_eq!(status.attempts, 3);
+
+        //
+        // A stable run resets them
+        status.start_running(start);
+        status.end_running(start + STABLE_RUNNING_PERIOD);
+        assert_eq!(status.attempts, 0);
+    }
+}
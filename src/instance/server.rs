@@ -1,11 +1,13 @@
 pub mod boolean;
 pub mod bytes;
+pub mod r#enum;
 pub mod json;
 pub mod notification;
 pub mod number;
 pub mod status;
 pub mod string;
 pub mod structure;
+pub mod vector;
 
 /// The standard object attribute server
 ///
@@ -26,6 +28,15 @@ pub type CallbackFn<T> =
 /// Type alias for condition function that filters events with generic type T
 pub type ConditionFn<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
 
+/// Type alias for the function that accepts or rejects the commands with generic type T
+///
+/// An accepted command is returned, possibly normalized, and given to the callbacks.
+pub type ValidatorFn<T> = Box<dyn Fn(T) -> Result<T, crate::Error> + Send + Sync>;
+
+/// The '<topic>/mode' alert is raised once for a burst of commands on a read only
+/// attribute, and cleared when no command has been received during this delay
+pub(crate) const MODE_ALERT_HOLD: std::time::Duration = std::time::Duration::from_secs(10);
+
 /// Asynchronous callback entry containing the callback and optional condition
 pub struct CallbackEntry<T> {
     pub callback: CallbackFn<T>,
@@ -1,4 +1,7 @@
+use crate::instance::element::Enablement;
 use crate::instance::server::StdObjAttributeServer;
+use crate::AlertSeverity;
+use crate::AttributeMode;
 use crate::Error;
 use crate::Logger;
 use crate::Notification;
@@ -28,12 +31,19 @@ impl BooleanAttributeServer {
     pub async fn new(
         session: Session,
         topic: String,
+        mode: AttributeMode,
         task_monitor_sender: Sender<NamedTaskHandle>,
         notification_channel: Sender<Notification>,
     ) -> Self {
-        let inner =
-            StdObjAttributeServer::new(session, topic, task_monitor_sender, notification_channel)
-                .await;
+        let inner = StdObjAttributeServer::new(
+            session,
+            topic,
+            mode,
+            None,
+            task_monitor_sender,
+            notification_channel,
+        )
+        .await;
 
         Self {
             inner: Arc::new(inner),
@@ -121,4 +131,43 @@ impl BooleanAttributeServer {
     ) -> impl std::future::Future<Output = ()> + '_ {
         self.inner.trigger_alert(message)
     }
+
+    ///
+    /// Trigger an alert with a severity
+    ///
+    #[inline]
+    pub fn trigger_alert_with_severity<T: Into<String> + 'static>(
+        &self,
+        severity: AlertSeverity,
+        message: T,
+    ) -> impl std::future::Future<Output = ()> + '_ {
+        self.inner.trigger_alert_with_severity(severity, message)
+    }
+
+    ///
+    /// Clear the alert
+    ///
+    #[inline]
+    pub fn clear_alert(&self) -> impl std::future::Future<Output = ()> + '_ {
+        self.inner.clear_alert()
+    }
+
+    ///
+    /// Enablement of the attribute
+    ///
+    #[inline]
+    pub fn enablement(&self) -> Enablement {
+        self.inner.enablement()
+    }
+
+    ///
+    /// Enable or disable the attribute
+    ///
+    #[inline]
+    pub fn change_enablement(
+        &self,
+        enabled: bool,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + '_ {
+        self.inner.change_enablement(enabled)
+    }
 }
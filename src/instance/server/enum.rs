@@ -0,0 +1,243 @@
+use crate::instance::element::Enablement;
+use crate::instance::server::{StdObjAttributeServer, ValidatorFn};
+use crate::AlertSeverity;
+use crate::AttributeMode;
+use crate::Error;
+use crate::Logger;
+use crate::Notification;
+use panduza::attribute::CallbackId;
+use panduza::fbs::StringBuffer;
+use panduza::task_monitor::NamedTaskHandle;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use zenoh::Session;
+
+#[derive(Clone)]
+///
+/// EnumAttributeServer provides a server for string attributes restricted to a list of choices
+///
+/// The choices are published in the settings of the attribute.
+///
+/// ## Json Settings
+///
+/// { "choices": ["low", "medium", "high"] }
+///
+pub struct EnumAttributeServer {
+    pub inner: Arc<StdObjAttributeServer<StringBuffer>>,
+
+    /// Accepted values
+    ///
+    choices: Arc<Vec<String>>,
+}
+
+impl EnumAttributeServer {
+    /// Logger getter
+    ///
+    pub fn logger(&self) -> &Logger {
+        self.inner.logger()
+    }
+
+    ///
+    ///
+    pub fn r#type() -> String {
+        "enum".to_string()
+    }
+
+    ///
+    /// Create a new EnumAttributeServer
+    ///
+    pub async fn new(
+        session: Session,
+        topic: String,
+        mode: AttributeMode,
+        choices: Vec<String>,
+        task_monitor_sender: Sender<NamedTaskHandle>,
+        notification_channel: Sender<Notification>,
+    ) -> Self {
+        //
+        // Reject the commands that are not among the choices
+        let choices = Arc::new(choices);
+        let validator_choices = choices.clone();
+        let validator: ValidatorFn<StringBuffer> = Box::new(
+            move |command: StringBuffer| -> Result<StringBuffer, Error> {
+                check_choice(&validator_choices, &command.value().to_string())?;
+                Ok(command)
+            },
+        );
+
+        let inner = StdObjAttributeServer::new(
+            session,
+            topic,
+            mode,
+            Some(validator),
+            task_monitor_sender,
+            notification_channel,
+        )
+        .await;
+
+        //
+        // Queries get a valid choice until the driver sets the value
+        if let Some(first) = choices.first() {
+            inner
+                .seed_value(
+                    StringBuffer::builder()
+                        .with_value(first.clone())
+                        .with_source(0)
+                        .with_random_sequence()
+                        .build()
+                        .unwrap(),
+                )
+                .await;
+        }
+
+        Self {
+            inner: Arc::new(inner),
+            choices: choices,
+        }
+    }
+
+    /// Accepted values
+    ///
+    pub fn choices(&self) -> &Vec<String> {
+        &self.choices
+    }
+
+    /// Set the value of the attribute
+    ///
+    /// Error::EnumOutOfChoices is returned if the value is not among the choices.
+    ///
+    pub async fn set<S>(&self, value: S) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let value = value.into();
+        check_choice(&self.choices, &value)?;
+        let buffer = StringBuffer::builder()
+            .with_value(value)
+            .with_source(0)
+            .with_random_sequence()
+            .build()
+            .unwrap();
+        self.inner.set(buffer).await
+    }
+
+    /// Ajoute un callback sans condition (toujours déclenché)
+    ///
+    #[inline]
+    pub fn add_callback<F>(&self, callback: F) -> impl std::future::Future<Output = CallbackId> + '_
+    where
+        F: Fn(StringBuffer) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.inner
+            .add_callback(callback, Option::<fn(&StringBuffer) -> bool>::None)
+    }
+
+    /// Ajoute un callback avec une condition personnalisée
+    ///
+    #[inline]
+    pub fn add_callback_with_condition<F, C>(
+        &self,
+        callback: F,
+        condition: C,
+    ) -> impl std::future::Future<Output = CallbackId> + '_
+    where
+        F: Fn(StringBuffer) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>>
+            + Send
+            + Sync
+            + 'static,
+        C: Fn(&StringBuffer) -> bool + Send + Sync + 'static,
+    {
+        self.inner.add_callback(callback, Some(condition))
+    }
+
+    /// Remove a callback by its ID
+    ///
+    #[inline]
+    pub fn remove_callback(
+        &self,
+        callback_id: CallbackId,
+    ) -> impl std::future::Future<Output = bool> + '_ {
+        self.inner.remove_callback(callback_id)
+    }
+
+    ///
+    /// Trigger an alert
+    ///
+    #[inline]
+    pub fn trigger_alert<T: Into<String> + 'static>(
+        &self,
+        message: T,
+    ) -> impl std::future::Future<Output = ()> + '_ {
+        self.inner.trigger_alert(message)
+    }
+
+    ///
+    /// Trigger an alert with a severity
+    ///
+    #[inline]
+    pub fn trigger_alert_with_severity<T: Into<String> + 'static>(
+        &self,
+        severity: AlertSeverity,
+        message: T,
+    ) -> impl std::future::Future<Output = ()> + '_ {
+        self.inner.trigger_alert_with_severity(severity, message)
+    }
+
+    ///
+    /// Clear the alert
+    ///
+    #[inline]
+    pub fn clear_alert(&self) -> impl std::future::Future<Output = ()> + '_ {
+        self.inner.clear_alert()
+    }
+
+    ///
+    /// Enablement of the attribute
+    ///
+    #[inline]
+    pub fn enablement(&self) -> Enablement {
+        self.inner.enablement()
+    }
+
+    ///
+    /// Enable or disable the attribute
+    ///
+    #[inline]
+    pub fn change_enablement(
+        &self,
+        enabled: bool,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + '_ {
+        self.inner.change_enablement(enabled)
+    }
+}
+
+/// Return an error if the value is not among the choices
+///
+pub fn check_choice(choices: &[String], value: &str) -> Result<(), Error> {
+    if choices.iter().any(|c| c == value) {
+        Ok(())
+    } else {
+        Err(Error::EnumOutOfChoices(format!(
+            "'{}' not in {:?}",
+            value, choices
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_choice() {
+        let choices = vec!["low".to_string(), "high".to_string()];
+        assert!(check_choice(&choices, "low").is_ok());
+        assert!(matches!(
+            check_choice(&choices, "medium"),
+            Err(Error::EnumOutOfChoices(_))
+        ));
+    }
+}
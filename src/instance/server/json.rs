@@ -1,4 +1,8 @@
+use super::MODE_ALERT_HOLD;
+use crate::instance::element::Enablement;
 use crate::AlertNotification;
+use crate::AlertSeverity;
+use crate::AttributeMode;
 use crate::Error;
 use crate::Logger;
 use crate::Notification;
@@ -62,6 +66,14 @@ pub struct JsonAttributeServer {
     /// Channel to send notifications
     ///
     notification_channel: Sender<Notification>,
+
+    /// Commands are ignored and nothing is published while disabled
+    ///
+    enablement: Enablement,
+
+    /// Access mode, enforced on the command and query paths
+    ///
+    mode: AttributeMode,
 }
 
 impl JsonAttributeServer {
@@ -82,6 +94,7 @@ impl JsonAttributeServer {
     pub async fn new(
         session: Session,
         topic: String,
+        mode: AttributeMode,
         cmd_receiver: Subscriber<FifoChannelHandler<Sample>>,
         task_monitor_sender: Sender<NamedTaskHandle>,
         notification_channel: Sender<Notification>,
@@ -90,32 +103,42 @@ impl JsonAttributeServer {
         //
         // let pack = Arc::new(Mutex::new(JsonDataPack::default()));
         let query_value = Arc::new(Mutex::new(JsonValue::Null));
+        let enablement = Enablement::new(topic.clone(), notification_channel.clone());
 
         // create a queryable to get value at initialization
         //
         let topic_clone = topic.clone();
         let session_clone = session.clone();
         let query_value_clone = query_value.clone();
-        let handle = tokio::spawn(async move {
-            let queryable = session_clone
-                .declare_queryable(format!("{}/att", topic_clone.clone()))
-                .await
-                .unwrap();
+        let enablement_clone = enablement.clone();
 
-            while let Ok(query) = queryable.recv_async().await {
-                let value = query_value_clone.lock().unwrap().clone(); // Clone the value
-                let pyl = Bytes::from(serde_json::to_string(&value).unwrap());
-                query
-                    .reply(format!("{}/att", topic_clone.clone()), pyl)
+        //
+        // A write only attribute has no value to give
+        if mode != AttributeMode::WriteOnly {
+            let handle = tokio::spawn(async move {
+                let queryable = session_clone
+                    .declare_queryable(format!("{}/att", topic_clone.clone()))
                     .await
                     .unwrap();
-            }
-            Ok(())
-        });
 
-        task_monitor_sender
-            .try_send((format!("{}/server/json", &topic), handle))
-            .unwrap();
+                while let Ok(query) = queryable.recv_async().await {
+                    if !enablement_clone.is_enabled() {
+                        continue;
+                    }
+                    let value = query_value_clone.lock().unwrap().clone(); // Clone the value
+                    let pyl = Bytes::from(serde_json::to_string(&value).unwrap());
+                    query
+                        .reply(format!("{}/att", topic_clone.clone()), pyl)
+                        .await
+                        .unwrap();
+                }
+                Ok(())
+            });
+
+            task_monitor_sender
+                .try_send((format!("{}/server/json", &topic), handle))
+                .unwrap();
+        }
 
         //
         //
@@ -128,14 +151,37 @@ impl JsonAttributeServer {
             topic: topic,
             current_value: query_value,
             notification_channel: notification_channel,
+            enablement: enablement,
+            mode: mode,
         }
     }
 
+    /// Access mode of the attribute
+    ///
+    pub fn mode(&self) -> &AttributeMode {
+        &self.mode
+    }
+
+    /// Enablement of the attribute
+    ///
+    pub fn enablement(&self) -> Enablement {
+        self.enablement.clone()
+    }
+
+    /// Enable or disable the attribute
+    ///
+    pub async fn change_enablement(&self, enabled: bool) -> Result<(), Error> {
+        self.enablement.change(enabled).await
+    }
+
     /// Set the value of the attribute
     ///
     pub async fn set(&self, value: JsonValue) -> Result<(), Error> {
         // update the current queriable value
         *self.current_value.lock().unwrap() = value.clone();
+        if !self.enablement.is_enabled() {
+            return Ok(());
+        }
 
         // Wrap value into payload
         let pyl = Bytes::from(serde_json::to_string(&value).unwrap());
@@ -151,16 +197,89 @@ impl JsonAttributeServer {
     ///
     ///
     pub async fn wait_for_commands(&self) -> Result<JsonValue, Error> {
-        let received = self.cmd_receiver.recv_async().await.unwrap();
-        let value: JsonValue = received.payload().try_to_string().unwrap().parse().unwrap();
-        Ok(value)
+        let mut mode_alert_raised = false;
+        loop {
+            //
+            // Clear the mode alert once the writes on the read only attribute stop
+            let received = match mode_alert_raised {
+                true => {
+                    match tokio::time::timeout(MODE_ALERT_HOLD, self.cmd_receiver.recv_async())
+                        .await
+                    {
+                        Ok(received) => received.unwrap(),
+                        Err(_) => {
+                            mode_alert_raised = false;
+                            self.notification_channel
+                                .send(
+                                    AlertNotification::cleared(
+                                        self.topic.clone(),
+                                        format!("{}/mode", self.topic),
+                                    )
+                                    .into(),
+                                )
+                                .await
+                                .unwrap();
+                            continue;
+                        }
+                    }
+                }
+                false => self.cmd_receiver.recv_async().await.unwrap(),
+            };
+            if !self.enablement.is_enabled() {
+                continue;
+            }
+            if self.mode == AttributeMode::ReadOnly {
+                if !mode_alert_raised {
+                    mode_alert_raised = true;
+                    self.notification_channel
+                        .send(
+                            AlertNotification::new(
+                                self.topic.clone(),
+                                "Command rejected, attribute is read only",
+                            )
+                            .with_id(format!("{}/mode", self.topic))
+                            .with_severity(AlertSeverity::Warning)
+                            .into(),
+                        )
+                        .await
+                        .unwrap();
+                }
+                continue;
+            }
+            let value: JsonValue = received.payload().try_to_string().unwrap().parse().unwrap();
+            return Ok(value);
+        }
     }
 
     ///
     ///
     pub async fn trigger_alert<T: Into<String>>(&self, message: T) {
-        let notification =
-            Notification::Alert(AlertNotification::new(self.topic.clone(), message.into()));
+        self.trigger_alert_with_severity(AlertSeverity::default(), message)
+            .await;
+    }
+
+    /// Raise the alert of the attribute with the given severity
+    ///
+    /// The alert id is the topic, a new alert replaces the previous one.
+    ///
+    pub async fn trigger_alert_with_severity<T: Into<String>>(
+        &self,
+        severity: AlertSeverity,
+        message: T,
+    ) {
+        let notification = Notification::Alert(
+            AlertNotification::new(self.topic.clone(), message).with_severity(severity),
+        );
+        self.notification_channel.send(notification).await.unwrap();
+    }
+
+    /// Clear the alert of the attribute
+    ///
+    pub async fn clear_alert(&self) {
+        let notification = Notification::Alert(AlertNotification::cleared(
+            self.topic.clone(),
+            self.topic.clone(),
+        ));
         self.notification_channel.send(notification).await.unwrap();
     }
 }
@@ -1,20 +1,157 @@
-use crate::instance::server::StdObjAttributeServer;
+use crate::instance::element::Enablement;
+use crate::instance::server::{StdObjAttributeServer, ValidatorFn};
+use crate::AlertSeverity;
+use crate::AttributeMode;
 use crate::Error;
 use crate::Logger;
 use crate::Notification;
 use panduza::attribute::CallbackId;
-use panduza::fbs::NumberBuffer;
+use panduza::fbs::{NumberBuffer, PzaBuffer};
 use panduza::task_monitor::NamedTaskHandle;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use zenoh::Session;
 
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+/// Constraints of a number attribute, declared on the builder
+///
+/// They are published in the attribute settings and enforced on the values
+/// set by the driver and on the commands of the clients.
+///
+/// ## Json Structure
+///
+/// { "min": 0.0, "max": 30.0, "whitelist": null, "unit": "V", "decimals": 2 }
+///
+pub struct NumberSettings {
+    /// Lowest accepted value
+    ///
+    pub min: Option<f64>,
+
+    /// Highest accepted value
+    ///
+    pub max: Option<f64>,
+
+    /// Only those values are accepted
+    ///
+    pub whitelist: Option<Vec<f64>>,
+
+    /// Unit of the value (V, A, Hz...)
+    ///
+    pub unit: Option<String>,
+
+    /// Values are rounded to this number of decimals
+    ///
+    pub decimals: Option<usize>,
+}
+
+impl NumberSettings {
+    /// True if there is nothing to publish or to enforce
+    ///
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Round the value to the declared decimals
+    ///
+    pub fn round(&self, value: f64) -> f64 {
+        match self.decimals {
+            Some(decimals) => {
+                let factor = 10f64.powi(decimals as i32);
+                (value * factor).round() / factor
+            }
+            None => value,
+        }
+    }
+
+    /// Largest difference between a value and a whitelist entry to match it
+    ///
+    /// Half of the last decimal when the decimals are declared, else the
+    /// precision of a f32 around the entry.
+    ///
+    pub fn tolerance(&self, entry: f64) -> f64 {
+        match self.decimals {
+            Some(decimals) => 0.5 * 10f64.powi(-(decimals as i32)),
+            None => f32::EPSILON as f64 * entry.abs().max(1.0),
+        }
+    }
+
+    /// Return an error if the value breaks a constraint
+    ///
+    pub fn check(&self, value: f64) -> Result<(), Error> {
+        //
+        // NaN would pass the range tests
+        if !value.is_finite() {
+            return Err(Error::SiOutOfRange(format!(
+                "{} is not a finite value",
+                value
+            )));
+        }
+        if let Some(min) = self.min {
+            if value < min {
+                return Err(Error::SiOutOfRange(format!("{} < min {}", value, min)));
+            }
+        }
+        if let Some(max) = self.max {
+            if value > max {
+                return Err(Error::SiOutOfRange(format!("{} > max {}", value, max)));
+            }
+        }
+        if let Some(whitelist) = &self.whitelist {
+            if !whitelist
+                .iter()
+                .any(|w| (w - value).abs() <= self.tolerance(*w))
+            {
+                return Err(Error::SiOutOfRange(format!(
+                    "{} not in whitelist {:?}",
+                    value, whitelist
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Round the value then check it
+    ///
+    pub fn apply(&self, value: f64) -> Result<f64, Error> {
+        let value = self.round(value);
+        self.check(value)?;
+        Ok(value)
+    }
+
+    /// Merge the constraints into the json settings of the attribute
+    ///
+    pub fn merge_into(&self, settings: Option<serde_json::Value>) -> Option<serde_json::Value> {
+        if self.is_empty() {
+            return settings;
+        }
+        let mut merged = match settings {
+            Some(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        if let serde_json::Value::Object(constraints) =
+            serde_json::to_value(self).expect("Failed to serialize NumberSettings to JSON")
+        {
+            for (key, value) in constraints {
+                if !value.is_null() {
+                    merged.insert(key, value);
+                }
+            }
+        }
+        Some(serde_json::Value::Object(merged))
+    }
+}
+
 #[derive(Clone)]
 ///
 /// NumberAttributeServer provides a server for numeric attributes
 ///
 pub struct NumberAttributeServer {
     pub inner: Arc<StdObjAttributeServer<NumberBuffer>>,
+
+    /// Constraints enforced on the values and the commands
+    ///
+    settings: Arc<NumberSettings>,
 }
 
 impl NumberAttributeServer {
@@ -30,12 +167,39 @@ impl NumberAttributeServer {
     pub async fn new(
         session: Session,
         topic: String,
+        mode: AttributeMode,
+        settings: NumberSettings,
         task_monitor_sender: Sender<NamedTaskHandle>,
         notification_channel: Sender<Notification>,
     ) -> Self {
+        //
+        // Round the commands like the values, then reject the ones that break the constraints
+        //
+        // Always installed, non-finite commands are rejected even without constraints.
+        // A rounded command keeps the source and the sequence of the client.
+        let settings = Arc::new(settings);
+        let validator_settings = settings.clone();
+        let validator: Option<ValidatorFn<NumberBuffer>> = Some(Box::new(
+            move |command: NumberBuffer| -> Result<NumberBuffer, Error> {
+                let value = validator_settings.apply(command.value())?;
+                if value == command.value() {
+                    return Ok(command);
+                }
+                let header = command.as_message().header().unwrap();
+                Ok(NumberBuffer::builder()
+                    .with_value(value)
+                    .with_source(header.source())
+                    .with_sequence(header.sequence())
+                    .build()
+                    .unwrap())
+            },
+        ));
+
         let inner = StdObjAttributeServer::<NumberBuffer>::new(
             session,
             topic,
+            mode,
+            validator,
             task_monitor_sender,
             notification_channel,
         )
@@ -43,17 +207,28 @@ impl NumberAttributeServer {
 
         Self {
             inner: Arc::new(inner),
+            settings: settings,
         }
     }
 
+    /// Constraints of the attribute
+    ///
+    pub fn settings(&self) -> &NumberSettings {
+        &self.settings
+    }
+
     /// Set the value of the attribute
     ///
+    /// The value is rounded to the declared decimals, Error::SiOutOfRange is
+    /// returned if it breaks the range or the whitelist.
+    ///
     pub async fn set<V>(&self, value: V) -> Result<(), Error>
     where
         V: Into<f64>,
     {
+        let value = self.settings.apply(value.into())?;
         let buffer = NumberBuffer::builder()
-            .with_value(value.into())
+            .with_value(value)
             .with_source(0)
             .with_random_sequence()
             .build()
@@ -61,64 +236,44 @@ impl NumberAttributeServer {
         self.inner.set(buffer).await
     }
 
-    /// Set the value with specific unit
+    /// Set the value, the unit is now declared on the builder
     ///
+    #[deprecated(note = "declare the unit with AttributeServerBuilder::with_unit")]
     pub async fn with_unit<V>(&self, value: V) -> Result<(), Error>
     where
         V: Into<f64>,
     {
-        let buffer = NumberBuffer::builder()
-            .with_value(value.into())
-            .with_source(0)
-            .with_random_sequence()
-            .build()
-            .unwrap();
-        self.inner.set(buffer).await
+        self.set(value).await
     }
 
-    /// Set the value with decimals
+    /// Set the value, the decimals are now declared on the builder
     ///
+    #[deprecated(note = "declare the decimals with AttributeServerBuilder::with_decimals")]
     pub async fn with_decimals<V>(&self, value: V, _decimals: u8) -> Result<(), Error>
     where
         V: Into<f64>,
     {
-        let buffer = NumberBuffer::builder()
-            .with_value(value.into())
-            .with_source(0)
-            .with_random_sequence()
-            .build()
-            .unwrap();
-        self.inner.set(buffer).await
+        self.set(value).await
     }
 
-    /// Set the value with range constraints
+    /// Set the value, the range is now declared on the builder
     ///
+    #[deprecated(note = "declare the range with AttributeServerBuilder::with_range")]
     pub async fn with_range<V>(&self, value: V, _min: f64, _max: f64) -> Result<(), Error>
     where
         V: Into<f64>,
     {
-        let buffer = NumberBuffer::builder()
-            .with_value(value.into())
-            .with_source(0)
-            .with_random_sequence()
-            .build()
-            .unwrap();
-        self.inner.set(buffer).await
+        self.set(value).await
     }
 
-    /// Set the value with whitelist constraints
+    /// Set the value, the whitelist is now declared on the builder
     ///
+    #[deprecated(note = "declare the whitelist with AttributeServerBuilder::with_whitelist")]
     pub async fn with_whitelist<V>(&self, value: V, _whitelist: Vec<f64>) -> Result<(), Error>
     where
         V: Into<f64>,
     {
-        let buffer = NumberBuffer::builder()
-            .with_value(value.into())
-            .with_source(0)
-            .with_random_sequence()
-            .build()
-            .unwrap();
-        self.inner.set(buffer).await
+        self.set(value).await
     }
 
     /// Ajoute un callback sans condition (toujours déclenché)
@@ -173,4 +328,91 @@ impl NumberAttributeServer {
     ) -> impl std::future::Future<Output = ()> + '_ {
         self.inner.trigger_alert(message)
     }
+
+    ///
+    /// Trigger an alert with a severity
+    ///
+    #[inline]
+    pub fn trigger_alert_with_severity<T: Into<String> + 'static>(
+        &self,
+        severity: AlertSeverity,
+        message: T,
+    ) -> impl std::future::Future<Output = ()> + '_ {
+        self.inner.trigger_alert_with_severity(severity, message)
+    }
+
+    ///
+    /// Clear the alert
+    ///
+    #[inline]
+    pub fn clear_alert(&self) -> impl std::future::Future<Output = ()> + '_ {
+        self.inner.clear_alert()
+    }
+
+    ///
+    /// Enablement of the attribute
+    ///
+    #[inline]
+    pub fn enablement(&self) -> Enablement {
+        self.inner.enablement()
+    }
+
+    ///
+    /// Enable or disable the attribute
+    ///
+    #[inline]
+    pub fn change_enablement(
+        &self,
+        enabled: bool,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + '_ {
+        self.inner.change_enablement(enabled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_settings() {
+        let settings = NumberSettings {
+            min: Some(0.0),
+            max: Some(5.0),
+            decimals: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(settings.apply(3.14159).unwrap(), 3.14);
+        assert!(matches!(settings.apply(5.5), Err(Error::SiOutOfRange(_))));
+        assert!(matches!(
+            settings.apply(f64::NAN),
+            Err(Error::SiOutOfRange(_))
+        ));
+        assert!(NumberSettings::default().check(f64::INFINITY).is_err());
+
+        let whitelist = NumberSettings {
+            whitelist: Some(vec![1.0, 2.0]),
+            unit: Some("V".to_string()),
+            ..Default::default()
+        };
+        assert!(whitelist.check(2.0).is_ok());
+        assert!(whitelist.check(2.0f32 as f64 + 1e-7).is_ok());
+        assert!(whitelist.check(1.5).is_err());
+
+        //
+        // A value rounded to the decimals matches its whitelist entry
+        let rounded = NumberSettings {
+            whitelist: Some(vec![0.1, 0.2]),
+            decimals: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(rounded.apply(0.1f32 as f64).unwrap(), 0.1);
+        assert!(rounded.apply(0.3).is_err());
+
+        let merged = whitelist
+            .merge_into(Some(serde_json::json!({ "info": "x" })))
+            .unwrap();
+        assert_eq!(merged["unit"], "V");
+        assert_eq!(merged["info"], "x");
+        assert!(merged.get("min").is_none());
+    }
 }
@@ -1,4 +1,6 @@
+use crate::instance::element::Enablement;
 use crate::AlertNotification;
+use crate::AlertSeverity;
 use crate::Error;
 use crate::Logger;
 use crate::Notification;
@@ -23,6 +25,9 @@ pub struct RoStreamAttributeServer {
 
     /// Channel to send notifications
     notification_channel: Sender<Notification>,
+
+    /// Nothing is pushed while disabled
+    enablement: Enablement,
 }
 
 impl RoStreamAttributeServer {
@@ -47,14 +52,31 @@ impl RoStreamAttributeServer {
             logger: Logger::new_for_attribute_from_topic(topic.clone()),
             session: session,
             att_topic: att_topic,
+            enablement: Enablement::new(topic.clone(), notification_channel.clone()),
             topic: topic,
             notification_channel: notification_channel,
         }
     }
 
+    /// Enablement of the attribute
+    ///
+    pub fn enablement(&self) -> Enablement {
+        self.enablement.clone()
+    }
+
+    /// Enable or disable the attribute
+    ///
+    pub async fn change_enablement(&self, enabled: bool) -> Result<(), Error> {
+        self.enablement.change(enabled).await
+    }
+
     ///
     ///
     pub async fn push<B: PzaBuffer>(&self, buffer: B) -> Result<(), Error> {
+        if !self.enablement.is_enabled() {
+            return Ok(());
+        }
+
         // Send the command
         self.session
             .put(&self.att_topic, buffer.to_zbytes())
@@ -66,8 +88,32 @@ impl RoStreamAttributeServer {
     ///
     ///
     pub async fn trigger_alert<T: Into<String>>(&self, message: T) {
-        let notification =
-            Notification::Alert(AlertNotification::new(self.topic.clone(), message.into()));
+        self.trigger_alert_with_severity(AlertSeverity::default(), message)
+            .await;
+    }
+
+    /// Raise the alert of the attribute with the given severity
+    ///
+    /// The alert id is the topic, a new alert replaces the previous one.
+    ///
+    pub async fn trigger_alert_with_severity<T: Into<String>>(
+        &self,
+        severity: AlertSeverity,
+        message: T,
+    ) {
+        let notification = Notification::Alert(
+            AlertNotification::new(self.topic.clone(), message).with_severity(severity),
+        );
+        self.notification_channel.send(notification).await.unwrap();
+    }
+
+    /// Clear the alert of the attribute
+    ///
+    pub async fn clear_alert(&self) {
+        let notification = Notification::Alert(AlertNotification::cleared(
+            self.topic.clone(),
+            self.topic.clone(),
+        ));
         self.notification_channel.send(notification).await.unwrap();
     }
 }
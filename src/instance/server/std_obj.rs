@@ -1,6 +1,10 @@
-use super::{CallbackEntry, CallbackId};
+use super::{CallbackEntry, CallbackId, ValidatorFn, MODE_ALERT_HOLD};
+use crate::instance::element::Enablement;
 use crate::log_debug;
+use crate::log_warn;
 use crate::AlertNotification;
+use crate::AlertSeverity;
+use crate::AttributeMode;
 use crate::Error;
 use crate::Logger;
 use crate::Notification;
@@ -38,6 +42,15 @@ pub struct StdObjAttributeServer<B: PzaBuffer> {
 
     /// Current value
     current_value: Arc<Mutex<B>>,
+
+    /// Commands are rejected and nothing is published while disabled
+    enablement: Enablement,
+
+    /// Access mode, enforced on the command and query paths
+    mode: AttributeMode,
+
+    /// Commands rejected by this function do not reach the callbacks
+    validator: Arc<Mutex<Option<ValidatorFn<B>>>>,
 }
 
 impl<B: PzaBuffer> StdObjAttributeServer<B> {
@@ -47,11 +60,15 @@ impl<B: PzaBuffer> StdObjAttributeServer<B> {
         &self.logger
     }
 
+    /// Create the server and start its tasks
     ///
+    /// The validator is installed before the command task starts, so it sees every command.
     ///
     pub async fn new(
         session: Session,
         topic: String,
+        mode: AttributeMode,
+        validator: Option<ValidatorFn<B>>,
         task_monitor_sender: Sender<NamedTaskHandle>,
         notification_channel: Sender<Notification>,
     ) -> Self {
@@ -67,26 +84,35 @@ impl<B: PzaBuffer> StdObjAttributeServer<B> {
 
         //
         let query_value = Arc::new(Mutex::new(B::default()));
+        let enablement = Enablement::new(topic.clone(), notification_channel.clone());
+        let validator = Arc::new(Mutex::new(validator));
 
         //
-        let handle_query_processing = tokio::spawn(task_query_processing::<B>(
-            logger.clone(),
-            session.clone(),
-            att_topic.clone(),
-            query_value.clone(),
-        ));
-
-        //
-        task_monitor_sender
-            .send((format!("{}/ATT/QRY", &topic), handle_query_processing))
-            .await
-            .unwrap();
+        // A write only attribute has no value to give
+        if mode != AttributeMode::WriteOnly {
+            let handle_query_processing = tokio::spawn(task_query_processing::<B>(
+                logger.clone(),
+                session.clone(),
+                att_topic.clone(),
+                query_value.clone(),
+                enablement.clone(),
+            ));
+            task_monitor_sender
+                .send((format!("{}/ATT/QRY", &topic), handle_query_processing))
+                .await
+                .unwrap();
+        }
 
         //
         let handle_command_processing = tokio::spawn(task_command_processing::<B>(
+            logger.clone(),
             session.clone(),
             cmd_topic.clone(),
             callbacks.clone(),
+            enablement.clone(),
+            mode.clone(),
+            validator.clone(),
+            notification_channel.clone(),
         ));
 
         //
@@ -105,9 +131,55 @@ impl<B: PzaBuffer> StdObjAttributeServer<B> {
             topic: topic,
             notification_channel: notification_channel,
             current_value: query_value.clone(),
+            enablement: enablement,
+            mode: mode,
+            validator: validator,
         }
     }
 
+    /// Replace the function that accepts or rejects the incoming commands
+    ///
+    /// Prefer giving it to the constructor, the commands received before this call are not checked.
+    /// The callbacks receive the command returned by the function, it can normalize it.
+    /// A rejected command raises a warning alert with the error and is not given to the callbacks.
+    ///
+    pub async fn set_command_validator<F>(&self, validator: F)
+    where
+        F: Fn(B) -> Result<B, Error> + Send + Sync + 'static,
+    {
+        *self.validator.lock().await = Some(Box::new(validator));
+    }
+
+    /// Set the value given to the queries, without publishing it
+    ///
+    pub(crate) async fn seed_value(&self, buffer: B) {
+        *self.current_value.lock().await = buffer;
+    }
+
+    /// Topic of the attribute
+    ///
+    pub fn topic(&self) -> &String {
+        &self.topic
+    }
+
+    /// Access mode of the attribute
+    ///
+    pub fn mode(&self) -> &AttributeMode {
+        &self.mode
+    }
+
+    /// Enablement of the attribute
+    ///
+    pub fn enablement(&self) -> Enablement {
+        self.enablement.clone()
+    }
+
+    /// Enable or disable the attribute
+    ///
+    pub async fn change_enablement(&self, enabled: bool) -> Result<(), Error> {
+        self.enablement.change(enabled).await
+    }
+
     ///
     ///
     pub async fn set<T>(&self, value: T) -> Result<(), Error>
@@ -116,6 +188,12 @@ impl<B: PzaBuffer> StdObjAttributeServer<B> {
     {
         let buffer: B = value.into();
 
+        // Keep the value but stay silent while disabled
+        if !self.enablement.is_enabled() {
+            *self.current_value.lock().await = buffer;
+            return Ok(());
+        }
+
         // Send the command
         self.session
             .put(&self.att_topic, buffer.clone().to_zbytes())
@@ -152,8 +230,32 @@ impl<B: PzaBuffer> StdObjAttributeServer<B> {
     ///
     ///
     pub async fn trigger_alert<T: Into<String>>(&self, message: T) {
-        let notification =
-            Notification::Alert(AlertNotification::new(self.topic.clone(), message.into()));
+        self.trigger_alert_with_severity(AlertSeverity::default(), message)
+            .await;
+    }
+
+    /// Raise the alert of the attribute with the given severity
+    ///
+    /// The alert id is the topic, a new alert replaces the previous one.
+    ///
+    pub async fn trigger_alert_with_severity<T: Into<String>>(
+        &self,
+        severity: AlertSeverity,
+        message: T,
+    ) {
+        let notification = Notification::Alert(
+            AlertNotification::new(self.topic.clone(), message).with_severity(severity),
+        );
+        self.notification_channel.send(notification).await.unwrap();
+    }
+
+    /// Clear the alert of the attribute
+    ///
+    pub async fn clear_alert(&self) {
+        let notification = Notification::Alert(AlertNotification::cleared(
+            self.topic.clone(),
+            self.topic.clone(),
+        ));
         self.notification_channel.send(notification).await.unwrap();
     }
 
@@ -204,20 +306,110 @@ impl<B: PzaBuffer> StdObjAttributeServer<B> {
 /// Task command processing function that listens for commands and triggers callbacks
 ///
 pub async fn task_command_processing<B: PzaBuffer + Send + Sync + 'static>(
+    logger: Logger,
     session: zenoh::Session,
     cmd_topic: String,
     callbacks: std::sync::Arc<
         tokio::sync::Mutex<std::collections::HashMap<CallbackId, CallbackEntry<B>>>,
     >,
+    enablement: Enablement,
+    mode: AttributeMode,
+    validator: Arc<Mutex<Option<ValidatorFn<B>>>>,
+    notification_channel: Sender<Notification>,
 ) -> Result<(), String> {
     // Declare the command subscriber
     let cmd_subscriber = session.declare_subscriber(&cmd_topic).await.unwrap();
+    let topic = cmd_topic
+        .strip_suffix("/cmd")
+        .unwrap_or(&cmd_topic)
+        .to_string();
+
+    // Alerts raised by the rejected commands
+    let mut mode_alert_raised = false;
+    let mut command_alert_raised = false;
 
     // Loop to receive commands asynchronously
-    while let Ok(sample) = cmd_subscriber.recv_async().await {
+    loop {
+        // Clear the mode alert once the writes on the read only attribute stop
+        let received = match mode_alert_raised {
+            true => {
+                match tokio::time::timeout(MODE_ALERT_HOLD, cmd_subscriber.recv_async()).await {
+                    Ok(received) => received,
+                    Err(_) => {
+                        mode_alert_raised = false;
+                        let cleared = AlertNotification::cleared(&topic, format!("{}/mode", topic));
+                        notification_channel
+                            .send(cleared.into())
+                            .await
+                            .map_err(|e| e.to_string())?;
+                        continue;
+                    }
+                }
+            }
+            false => cmd_subscriber.recv_async().await,
+        };
+        let Ok(sample) = received else {
+            break;
+        };
+
+        // Reject commands while disabled
+        if !enablement.is_enabled() {
+            log_debug!(logger, "Command ignored, attribute disabled");
+            continue;
+        }
+
+        // Nobody is allowed to write a read only attribute
+        if mode == AttributeMode::ReadOnly {
+            log_warn!(logger, "Command rejected, attribute is read only");
+            if !mode_alert_raised {
+                mode_alert_raised = true;
+                let alert =
+                    AlertNotification::new(&topic, "Command rejected, attribute is read only")
+                        .with_id(format!("{}/mode", topic))
+                        .with_severity(AlertSeverity::Warning);
+                notification_channel
+                    .send(alert.into())
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            continue;
+        }
+
         // Create Buffer from the received zbytes
         let buffer = B::from_zbytes(sample.payload().clone());
 
+        // Reject the commands that break the constraints of the attribute
+        let validated = match validator.lock().await.as_ref() {
+            Some(validator) => validator(buffer),
+            None => Ok(buffer),
+        };
+        let buffer = match validated {
+            Ok(buffer) => buffer,
+            Err(error) => {
+                log_warn!(logger, "Command rejected {:?}", error);
+                command_alert_raised = true;
+                let alert =
+                    AlertNotification::new(&topic, format!("Command rejected ({:?})", error))
+                        .with_id(format!("{}/command", topic))
+                        .with_severity(AlertSeverity::Warning);
+                notification_channel
+                    .send(alert.into())
+                    .await
+                    .map_err(|e| e.to_string())?;
+                continue;
+            }
+        };
+
+        // The command is accepted, the previous rejection is over
+        if command_alert_raised {
+            command_alert_raised = false;
+            let cleared = AlertNotification::cleared(&topic, format!("{}/command", topic));
+            notification_channel
+                .send(cleared.into())
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
         // Trigger all async callbacks
         let callbacks_map = callbacks.lock().await;
         let mut futures = Vec::new();
@@ -252,6 +444,7 @@ pub async fn task_query_processing<B: PzaBuffer + Send + Sync + 'static>(
     session: zenoh::Session,
     att_topic: String,
     query_value: std::sync::Arc<tokio::sync::Mutex<B>>,
+    enablement: Enablement,
 ) -> Result<(), String> {
     let queryable = session
         .declare_queryable(&att_topic)
@@ -263,6 +456,10 @@ pub async fn task_query_processing<B: PzaBuffer + Send + Sync + 'static>(
     //     &att_topic
     // );
     while let Ok(query) = queryable.recv_async().await {
+        // A disabled attribute is hidden
+        if !enablement.is_enabled() {
+            continue;
+        }
         // log_debug!(
         //     logger,
         //     "[StdObjAttributeServer] Received query for topic: {}",
@@ -1,4 +1,5 @@
 use crate::instance::server::std_obj::StdObjAttributeServer;
+use crate::AttributeMode;
 use crate::Error;
 use crate::Logger;
 use crate::Notification;
@@ -33,13 +34,20 @@ impl StructureAttributeServer {
     pub async fn new(
         session: Session,
         topic: String,
+        mode: AttributeMode,
         task_monitor_sender: Sender<NamedTaskHandle>,
         notification_channel: Sender<Notification>,
     ) -> Self {
         // Initialize the inner implementation
-        let inner =
-            StdObjAttributeServer::new(session, topic, task_monitor_sender, notification_channel)
-                .await;
+        let inner = StdObjAttributeServer::new(
+            session,
+            topic,
+            mode,
+            None,
+            task_monitor_sender,
+            notification_channel,
+        )
+        .await;
 
         Self {
             inner: Arc::new(inner),
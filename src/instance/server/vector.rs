@@ -0,0 +1,379 @@
+use crate::instance::element::Enablement;
+use crate::instance::server::StdObjAttributeServer;
+use crate::AlertSeverity;
+use crate::AttributeMode;
+use crate::Error;
+use crate::Logger;
+use crate::Notification;
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+use bytes::Bytes;
+use panduza::fbs::BytesBuffer;
+use panduza::task_monitor::NamedTaskHandle;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::Mutex;
+use zenoh::Session;
+
+/// Size of the frame header before the unit string
+///
+const VECTOR_HEADER_FIXED_SIZE: usize = 1 + 4 + 8 + 8 + 2;
+
+/// Sample types that can be carried by a vector attribute
+///
+pub trait VectorSample: Copy + Send + Sync + 'static {
+    /// Code of the type in the frame header
+    ///
+    const CODE: u8;
+
+    /// Name of the type in the attribute type ("vector_f32"...)
+    ///
+    const NAME: &'static str;
+
+    /// Size of one sample in bytes
+    ///
+    const SIZE: usize;
+
+    /// Append the samples to the buffer in little endian
+    ///
+    fn write_samples(samples: &[Self], buffer: &mut Vec<u8>);
+
+    /// Read the samples from a little endian buffer
+    ///
+    fn read_samples(data: &[u8]) -> Vec<Self>;
+}
+
+impl VectorSample for f32 {
+    const CODE: u8 = 0x01;
+    const NAME: &'static str = "f32";
+    const SIZE: usize = 4;
+
+    fn write_samples(samples: &[Self], buffer: &mut Vec<u8>) {
+        let start = buffer.len();
+        buffer.resize(start + samples.len() * Self::SIZE, 0);
+        LittleEndian::write_f32_into(samples, &mut buffer[start..]);
+    }
+
+    fn read_samples(data: &[u8]) -> Vec<Self> {
+        let mut samples = vec![0.0; data.len() / Self::SIZE];
+        LittleEndian::read_f32_into(data, &mut samples);
+        samples
+    }
+}
+
+impl VectorSample for f64 {
+    const CODE: u8 = 0x02;
+    const NAME: &'static str = "f64";
+    const SIZE: usize = 8;
+
+    fn write_samples(samples: &[Self], buffer: &mut Vec<u8>) {
+        let start = buffer.len();
+        buffer.resize(start + samples.len() * Self::SIZE, 0);
+        LittleEndian::write_f64_into(samples, &mut buffer[start..]);
+    }
+
+    fn read_samples(data: &[u8]) -> Vec<Self> {
+        let mut samples = vec![0.0; data.len() / Self::SIZE];
+        LittleEndian::read_f64_into(data, &mut samples);
+        samples
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+/// Description of the samples of an acquisition
+///
+pub struct VectorMetadata {
+    /// Samples per second, 0 if unknown
+    ///
+    pub sample_rate: f64,
+
+    /// X value of the first sample (time of the first sample for a waveform)
+    ///
+    pub x_origin: f64,
+
+    /// Unit of the samples (V, A...)
+    ///
+    pub unit: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// One acquisition, encoded in the payload of a BytesBuffer
+///
+/// ## Frame (little endian)
+///
+/// | Size     | Field                                 |
+/// |----------|---------------------------------------|
+/// | u8       | sample type (0x01 f32, 0x02 f64)      |
+/// | u32      | number of samples                     |
+/// | f64      | sample rate                           |
+/// | f64      | x origin                              |
+/// | u16      | size of the unit                      |
+/// | n bytes  | unit (utf8)                           |
+/// | samples  | samples                               |
+///
+pub struct VectorFrame<T: VectorSample> {
+    /// Description of the samples
+    ///
+    pub metadata: VectorMetadata,
+
+    /// Samples
+    ///
+    pub samples: Vec<T>,
+}
+
+impl<T: VectorSample> VectorFrame<T> {
+    /// Encode the frame
+    ///
+    pub fn encode(&self) -> Result<Bytes, Error> {
+        let unit = self.metadata.unit.as_bytes();
+        let unit_len = u16::try_from(unit.len())
+            .map_err(|_| Error::CodecError(format!("Unit too long ({} bytes)", unit.len())))?;
+        let count = u32::try_from(self.samples.len())
+            .map_err(|_| Error::CodecError(format!("Too many samples ({})", self.samples.len())))?;
+
+        let mut buffer = Vec::with_capacity(
+            VECTOR_HEADER_FIXED_SIZE + unit.len() + self.samples.len() * T::SIZE,
+        );
+        buffer.push(T::CODE);
+        buffer.write_u32::<LittleEndian>(count).unwrap();
+        buffer
+            .write_f64::<LittleEndian>(self.metadata.sample_rate)
+            .unwrap();
+        buffer
+            .write_f64::<LittleEndian>(self.metadata.x_origin)
+            .unwrap();
+        buffer.write_u16::<LittleEndian>(unit_len).unwrap();
+        buffer.extend_from_slice(unit);
+        T::write_samples(&self.samples, &mut buffer);
+        Ok(Bytes::from(buffer))
+    }
+
+    /// Decode a frame
+    ///
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        let too_short = |_| Error::CodecError("Vector frame too short".to_string());
+        let mut cursor = Cursor::new(data);
+        let code = cursor.read_u8().map_err(too_short)?;
+        if code != T::CODE {
+            return Err(Error::CodecError(format!(
+                "Vector frame of type {:#04x}, expected {:#04x}",
+                code,
+                T::CODE
+            )));
+        }
+        let count = cursor.read_u32::<LittleEndian>().map_err(too_short)? as usize;
+        let sample_rate = cursor.read_f64::<LittleEndian>().map_err(too_short)?;
+        let x_origin = cursor.read_f64::<LittleEndian>().map_err(too_short)?;
+        let unit_len = cursor.read_u16::<LittleEndian>().map_err(too_short)? as usize;
+
+        let start = cursor.position() as usize;
+        let samples_start = start + unit_len;
+        let samples_end = samples_start + count * T::SIZE;
+        if data.len() < samples_end {
+            return Err(Error::CodecError("Vector frame too short".to_string()));
+        }
+        let unit = String::from_utf8(data[start..samples_start].to_vec())
+            .map_err(|e| Error::CodecError(format!("Invalid unit ({:?})", e)))?;
+
+        Ok(Self {
+            metadata: VectorMetadata {
+                sample_rate: sample_rate,
+                x_origin: x_origin,
+                unit: unit,
+            },
+            samples: T::read_samples(&data[samples_start..samples_end]),
+        })
+    }
+}
+
+#[derive(Clone)]
+///
+/// VectorAttributeServer publishes acquisitions (waveforms, spectrums...) of f32 or f64 samples
+///
+/// The last acquisition stays available to the queries.
+///
+pub struct VectorAttributeServer<T: VectorSample> {
+    pub inner: Arc<StdObjAttributeServer<BytesBuffer>>,
+
+    /// Metadata used by 'set'
+    ///
+    metadata: Arc<Mutex<VectorMetadata>>,
+
+    ///
+    ///
+    _sample: PhantomData<T>,
+}
+
+/// Vector of f32 samples
+///
+pub type VectorF32AttributeServer = VectorAttributeServer<f32>;
+
+/// Vector of f64 samples
+///
+pub type VectorF64AttributeServer = VectorAttributeServer<f64>;
+
+impl<T: VectorSample> VectorAttributeServer<T> {
+    /// Logger getter
+    ///
+    pub fn logger(&self) -> &Logger {
+        self.inner.logger()
+    }
+
+    ///
+    ///
+    pub fn r#type() -> String {
+        format!("vector_{}", T::NAME)
+    }
+
+    ///
+    /// Create a new VectorAttributeServer
+    ///
+    pub async fn new(
+        session: Session,
+        topic: String,
+        mode: AttributeMode,
+        metadata: VectorMetadata,
+        task_monitor_sender: Sender<NamedTaskHandle>,
+        notification_channel: Sender<Notification>,
+    ) -> Self {
+        let inner = StdObjAttributeServer::<BytesBuffer>::new(
+            session,
+            topic,
+            mode,
+            None,
+            task_monitor_sender,
+            notification_channel,
+        )
+        .await;
+
+        Self {
+            inner: Arc::new(inner),
+            metadata: Arc::new(Mutex::new(metadata)),
+            _sample: PhantomData,
+        }
+    }
+
+    /// Metadata used by 'set'
+    ///
+    pub async fn metadata(&self) -> VectorMetadata {
+        self.metadata.lock().await.clone()
+    }
+
+    /// Change the metadata used by 'set' (new timebase, new unit...)
+    ///
+    pub async fn set_metadata(&self, metadata: VectorMetadata) {
+        *self.metadata.lock().await = metadata;
+    }
+
+    /// Publish an acquisition with the current metadata
+    ///
+    pub async fn set(&self, samples: &[T]) -> Result<(), Error> {
+        let metadata = self.metadata.lock().await.clone();
+        self.publish(VectorFrame {
+            metadata: metadata,
+            samples: samples.to_vec(),
+        })
+        .await
+    }
+
+    /// Publish an acquisition with its own metadata, they become the current metadata
+    ///
+    pub async fn set_with_metadata(
+        &self,
+        samples: &[T],
+        metadata: VectorMetadata,
+    ) -> Result<(), Error> {
+        *self.metadata.lock().await = metadata.clone();
+        self.publish(VectorFrame {
+            metadata: metadata,
+            samples: samples.to_vec(),
+        })
+        .await
+    }
+
+    /// Encode and publish the frame
+    ///
+    async fn publish(&self, frame: VectorFrame<T>) -> Result<(), Error> {
+        let buffer = BytesBuffer::builder()
+            .with_value(frame.encode()?)
+            .with_source(0)
+            .with_random_sequence()
+            .build()
+            .unwrap();
+        self.inner.set(buffer).await
+    }
+
+    ///
+    /// Trigger an alert
+    ///
+    #[inline]
+    pub fn trigger_alert<M: Into<String> + 'static>(
+        &self,
+        message: M,
+    ) -> impl std::future::Future<Output = ()> + '_ {
+        self.inner.trigger_alert(message)
+    }
+
+    ///
+    /// Trigger an alert with a severity
+    ///
+    #[inline]
+    pub fn trigger_alert_with_severity<M: Into<String> + 'static>(
+        &self,
+        severity: AlertSeverity,
+        message: M,
+    ) -> impl std::future::Future<Output = ()> + '_ {
+        self.inner.trigger_alert_with_severity(severity, message)
+    }
+
+    ///
+    /// Clear the alert
+    ///
+    #[inline]
+    pub fn clear_alert(&self) -> impl std::future::Future<Output = ()> + '_ {
+        self.inner.clear_alert()
+    }
+
+    ///
+    /// Enablement of the attribute
+    ///
+    #[inline]
+    pub fn enablement(&self) -> Enablement {
+        self.inner.enablement()
+    }
+
+    ///
+    /// Enable or disable the attribute
+    ///
+    #[inline]
+    pub fn change_enablement(
+        &self,
+        enabled: bool,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + '_ {
+        self.inner.change_enablement(enabled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_frame() {
+        let frame = VectorFrame::<f32> {
+            metadata: VectorMetadata {
+                sample_rate: 1e6,
+                x_origin: -0.5,
+                unit: "V".to_string(),
+            },
+            samples: vec![0.0, 1.5, -2.25],
+        };
+        let encoded = frame.encode().unwrap();
+        assert_eq!(encoded.len(), VECTOR_HEADER_FIXED_SIZE + 1 + 3 * 4);
+        assert_eq!(VectorFrame::<f32>::decode(&encoded).unwrap(), frame);
+        assert!(VectorFrame::<f64>::decode(&encoded).is_err());
+        assert!(VectorFrame::<f32>::decode(&encoded[..10]).is_err());
+    }
+}
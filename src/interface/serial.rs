@@ -1,9 +1,11 @@
 pub mod common;
 pub mod eol;
+pub mod scanner;
 pub mod settings;
 pub mod slip;
 pub mod time_lock;
 
+pub use scanner::SerialScanner;
 pub use settings::Settings as SerialSettings;
 
 pub use eol::SerialEolInterface;
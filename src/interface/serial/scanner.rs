@@ -0,0 +1,78 @@
+use super::settings::SERIAL_PORT_NAME_KEY;
+use super::SerialSettings;
+use crate::interface::usb::scanner::instance_name;
+use crate::{Logger, ProductionOrder, Scanner};
+use crate::{SETTINGS_USB_PID_KEY, SETTINGS_USB_SERIAL_KEY, SETTINGS_USB_VID_KEY};
+
+/// Scanner that looks for usb serial ports matching a list of vid/pid
+///
+/// Each matching port is proposed with its port name and the usb settings
+/// (see `SerialSettings::set_port_name_from_json_or_usb_settings`)
+///
+pub struct SerialScanner {
+    /// Local logger
+    logger: Logger,
+    /// Reference of the driver that must be produced for the found devices
+    dref: String,
+    /// Usb identifiers (vid, pid) of the managed devices
+    ids: Vec<(u16, u16)>,
+}
+
+impl SerialScanner {
+    /// Create a new scanner for the driver 'dref'
+    ///
+    pub fn new<A: Into<String>>(dref: A) -> Self {
+        let dref = dref.into();
+        Self {
+            logger: Logger::new_isolated(format!("serial-scanner/{}", &dref)),
+            dref: dref,
+            ids: Vec::new(),
+        }
+    }
+
+    /// Add a vid/pid couple to look for
+    ///
+    pub fn with_ids(mut self, vid: u16, pid: u16) -> Self {
+        self.ids.push((vid, pid));
+        self
+    }
+}
+
+impl Scanner for SerialScanner {
+    fn name(&self) -> String {
+        format!("serial/{}", self.dref)
+    }
+
+    fn scan(&self) -> Vec<ProductionOrder> {
+        let mut result = Vec::new();
+        for (vid, pid) in &self.ids {
+            //
+            // Get the ports that match the identifiers, none if they cannot be listed
+            let ports = match SerialSettings::available_usb_serial_ports_with_ids(*vid, *pid) {
+                Ok(ports) => ports,
+                Err(e) => {
+                    self.logger
+                        .error(format!("Unable to list serial ports {:?}", e));
+                    Vec::new()
+                }
+            };
+
+            //
+            // Propose one instance per port
+            for (port_name, serial) in ports {
+                let mut order = ProductionOrder::new(
+                    &self.dref,
+                    instance_name(&self.dref, &serial, result.len()),
+                )
+                .add_string_setting(SERIAL_PORT_NAME_KEY, port_name)
+                .add_u16_setting(SETTINGS_USB_VID_KEY!(), *vid)
+                .add_u16_setting(SETTINGS_USB_PID_KEY!(), *pid);
+                if !serial.is_empty() {
+                    order = order.add_string_setting(SETTINGS_USB_SERIAL_KEY!(), serial);
+                }
+                result.push(order);
+            }
+        }
+        result
+    }
+}
@@ -1,6 +1,8 @@
+pub mod scanner;
 pub mod sdk_helper;
 pub mod settings;
 pub mod tmc;
 
+pub use scanner::UsbScanner;
 pub use settings::Settings as UsbSettings;
 pub use tmc::UsbTmcInterface;
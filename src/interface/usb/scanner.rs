@@ -0,0 +1,90 @@
+use crate::{Logger, ProductionOrder, Scanner};
+use crate::{SETTINGS_USB_PID_KEY, SETTINGS_USB_SERIAL_KEY, SETTINGS_USB_VID_KEY};
+
+/// Scanner that looks for usb devices matching a list of vid/pid
+///
+/// Each matching device is proposed with the usb settings that allow the
+/// driver to find it back (see `UsbSettings::optional_set_serial_from_json_settings`)
+///
+pub struct UsbScanner {
+    /// Local logger
+    logger: Logger,
+    /// Reference of the driver that must be produced for the found devices
+    dref: String,
+    /// Usb identifiers (vid, pid) of the managed devices
+    ids: Vec<(u16, u16)>,
+}
+
+impl UsbScanner {
+    /// Create a new scanner for the driver 'dref'
+    ///
+    pub fn new<A: Into<String>>(dref: A) -> Self {
+        let dref = dref.into();
+        Self {
+            logger: Logger::new_isolated(format!("usb-scanner/{}", &dref)),
+            dref: dref,
+            ids: Vec::new(),
+        }
+    }
+
+    /// Add a vid/pid couple to look for
+    ///
+    pub fn with_ids(mut self, vid: u16, pid: u16) -> Self {
+        self.ids.push((vid, pid));
+        self
+    }
+}
+
+impl Scanner for UsbScanner {
+    fn name(&self) -> String {
+        format!("usb/{}", self.dref)
+    }
+
+    fn scan(&self) -> Vec<ProductionOrder> {
+        let mut result = Vec::new();
+
+        //
+        // Get the usb device list
+        let devices = match nusb::list_devices() {
+            Ok(devices) => devices,
+            Err(e) => {
+                self.logger
+                    .error(format!("Unable to list usb devices {:?}", e));
+                return result;
+            }
+        };
+
+        //
+        // Keep only the managed devices
+        for dev in devices {
+            if !self.ids.contains(&(dev.vendor_id(), dev.product_id())) {
+                continue;
+            }
+
+            let serial = dev.serial_number().unwrap_or("").to_string();
+            let mut order =
+                ProductionOrder::new(&self.dref, instance_name(&self.dref, &serial, result.len()))
+                    .add_u16_setting(SETTINGS_USB_VID_KEY!(), dev.vendor_id())
+                    .add_u16_setting(SETTINGS_USB_PID_KEY!(), dev.product_id());
+            if !serial.is_empty() {
+                order = order.add_string_setting(SETTINGS_USB_SERIAL_KEY!(), serial);
+            }
+            result.push(order);
+        }
+
+        result
+    }
+}
+
+/// Build an instance name from the driver reference and the device serial number
+///
+/// The index is used when the device does not provide a serial number
+///
+pub fn instance_name(dref: &str, serial: &str, index: usize) -> String {
+    let base = dref.replace('.', "_");
+    if serial.is_empty() {
+        format!("{}_{}", base, index)
+    } else {
+        format!("{}_{}", base, serial)
+    }
+}
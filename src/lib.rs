@@ -20,6 +20,9 @@
 //     unused_parens
 // )]
 
+// Allow the derive macros to name this crate from the inside
+extern crate self as panduza_platform_core;
+
 /// Main error crate for Panduza Platform
 ///
 mod error;
@@ -61,7 +64,9 @@ pub use panduza::Topic;
 /// The engine is the core object that will handle the connections and the events
 ///
 mod engine;
+pub use engine::health::EngineHealth;
 pub use engine::new_engine;
+pub use engine::new_loopback_engine;
 pub use engine::options::EngineOptions;
 pub use engine::Engine;
 pub use engine::EngineBuilder;
@@ -69,7 +74,11 @@ pub use engine::EngineBuilder;
 /// Plugin object
 ///
 pub mod plugin;
+pub use plugin::loader::LoadedPlugin;
+pub use plugin::loader::PluginLoadOptions;
+pub use plugin::manager::PluginManager;
 pub use plugin::Plugin;
+pub use plugin::PluginCapabilities;
 
 ///
 ///
@@ -80,25 +89,37 @@ pub use factory::production_order::ProductionOrder;
 pub use factory::store::Product;
 pub use factory::store::Store;
 pub use factory::Factory;
-// pub use factory::ScanMachine;
+pub use factory::ScanMachine;
+pub use factory::Scanner;
 
 /// Manage an instance of a driver
 ///
 pub mod instance;
 pub use instance::actions::Actions;
+pub use instance::class::Class;
 pub use instance::container::Container;
+pub use instance::element::{Element, Enablement};
+pub use instance::reboot::{RebootPolicy, RebootStatus, RebootStrategy};
 pub use instance::Instance;
 
 ///
 ///
 pub mod runtime;
+pub use runtime::control::ControlCommand;
+pub use runtime::control::ControlRequest;
+pub use runtime::control::ControlResponse;
 pub use runtime::notification::attribute::AttributeMode;
 pub use runtime::notification::group::NotificationGroup;
-pub use runtime::notification::AlertNotification;
+pub use runtime::notification::group::SequencedNotification;
+pub use runtime::notification::publisher::NotificationPublisher;
 pub use runtime::notification::AttributeNotification;
 pub use runtime::notification::ClassNotification;
+pub use runtime::notification::EnablementNotification;
 pub use runtime::notification::Notification;
+pub use runtime::notification::OverflowNotification;
 pub use runtime::notification::StateNotification;
+pub use runtime::notification::{AlertNotification, AlertSeverity, AlertState};
+pub use runtime::structure::StructureRegistry;
 pub use runtime::Runtime;
 pub use runtime::RuntimeBuilder;
 
@@ -134,9 +155,22 @@ pub mod protocol;
 ///
 pub mod helper;
 
+/// Accessor models used by the templates to reach the driver values
 ///
+/// `#[derive(AccessorModel)]` implements them from the fields of a driver
+/// structure and generates its `mount_all(container)` function.
 ///
 pub mod model;
+pub use panduza_platform_core_macros::AccessorModel;
+
+// Used by the code generated by the derive macros
+#[doc(hidden)]
+pub use async_trait;
+
+/// Helpers to test drivers on a loopback engine, without any zenoh router
+///
+pub mod testing;
+pub use testing::TestBench;
 
 ///
 /// TODO => put in factory
@@ -146,6 +180,10 @@ pub use props::Prop;
 pub use props::PropType;
 pub use props::Props;
 
-// ///
-// ///
-// pub mod template;
+/// Mountable attributes and classes built on the accessor models
+///
+/// Mount a standard RW boolean or number from a BooleanAccessorModel or a
+/// NumberAccessorModel in one call.
+///
+pub mod template;
+pub use template::class::trigger::Triggerable;
@@ -60,3 +60,94 @@ pub trait VectorF32AccessorModel: Sync + Send {
 
 // acquisitor -> data ro + trigger
 // one or multiple vector of f32
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AccessorModel;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    #[derive(Clone, AccessorModel)]
+    struct FakeDriver {
+        #[attribute(
+            kind = "boolean",
+            info = "Output enable",
+            getter = "enable",
+            setter = "set_enable"
+        )]
+        enable: Arc<Mutex<bool>>,
+
+        #[attribute(
+            kind = "number",
+            unit = "V",
+            min = 0,
+            max = 30,
+            decimals = 2,
+            getter = "voltage",
+            setter = "set_voltage"
+        )]
+        voltage: Arc<Mutex<f64>>,
+
+        #[attribute(info = "Model of the supply")]
+        model: String,
+
+        #[attribute(
+            kind = "enum",
+            choices("cc", "cv"),
+            getter = "regulation",
+            setter = "set_regulation"
+        )]
+        regulation: Arc<Mutex<String>>,
+    }
+
+    impl FakeDriver {
+        async fn enable(&mut self) -> Result<bool, Error> {
+            Ok(*self.enable.lock().await)
+        }
+        async fn set_enable(&mut self, value: bool) -> Result<(), Error> {
+            *self.enable.lock().await = value;
+            Ok(())
+        }
+        async fn voltage(&mut self) -> Result<f64, Error> {
+            Ok(*self.voltage.lock().await)
+        }
+        async fn set_voltage(&mut self, value: f64) -> Result<(), Error> {
+            *self.voltage.lock().await = value;
+            Ok(())
+        }
+        async fn regulation(&mut self) -> Result<String, Error> {
+            Ok(self.regulation.lock().await.clone())
+        }
+        async fn set_regulation(&mut self, value: String) -> Result<(), Error> {
+            *self.regulation.lock().await = value;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_derive_accessor_model() {
+        let driver = FakeDriver {
+            enable: Arc::new(Mutex::new(false)),
+            voltage: Arc::new(Mutex::new(1.5)),
+            model: "PS-3005".to_string(),
+            regulation: Arc::new(Mutex::new("cv".to_string())),
+        };
+
+        //
+        // The attributes work on clones of the driver, like the attribute tasks
+        let mut clone = driver.clone();
+        clone.set_boolean_at(0, true).await.unwrap();
+        assert!(*driver.enable.lock().await);
+        assert!(clone.get_boolean_at(1).await.is_err());
+
+        assert_eq!(clone.get_number_at(0).await.unwrap(), 1.5);
+        clone.set_number_at(0, 12.0).await.unwrap();
+        assert_eq!(*driver.voltage.lock().await, 12.0);
+
+        assert_eq!(clone.get_string_at(0).await.unwrap(), "PS-3005");
+        assert!(clone.set_string_at(0, "other".to_string()).await.is_err());
+        clone.set_string_at(1, "cc".to_string()).await.unwrap();
+        assert_eq!(*driver.regulation.lock().await, "cc");
+    }
+}
@@ -1,14 +1,44 @@
+pub mod loader;
 pub mod macro_helper;
+pub mod manager;
+use bitflags::bitflags;
 use std::ffi::{c_char, CStr};
 
 use crate::Store;
 
 ///
 /// !!!!!
-/// Increment this number after a Plugin structure modification
+/// Increment the major after a breaking modification of the Plugin structure,
+/// the minor after a compatible addition (new capability, new optional symbol)
 /// !!!!!
 ///
-static C_INTERFACE_VERSION: u32 = 0;
+pub const C_INTERFACE_VERSION_MAJOR: u32 = 1;
+pub const C_INTERFACE_VERSION_MINOR: u32 = 0;
+
+///
+/// Version of the C interface, major in the high 16 bits and minor in the low 16 bits
+///
+pub static C_INTERFACE_VERSION: u32 = (C_INTERFACE_VERSION_MAJOR << 16) | C_INTERFACE_VERSION_MINOR;
+
+bitflags! {
+    /// Optional features of the plugin interface
+    ///
+    /// The plugin announces what it implements, the host keeps only what it supports too.
+    ///
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct PluginCapabilities: u32 {
+        /// 'scan' returns the instances found on the server
+        const SCAN = 1 << 0;
+        /// 'control' accepts runtime control requests (list, stop, restart...)
+        const CONTROL = 1 << 1;
+        /// 'plugin_entry_point' uses the engine config given by the host
+        const ENGINE_CONFIG = 1 << 2;
+        /// 'control' accepts the shutdown request, the runtime thread ends after it
+        const SHUTDOWN = 1 << 3;
+        /// 'structure' returns a snapshot of the instances, classes and attributes
+        const STRUCTURE = 1 << 4;
+    }
+}
 
 ///
 /// This structure provides the plugin interface
@@ -23,6 +53,13 @@ pub struct Plugin {
     ///
     pub c_interface_version: u32,
 
+    ///
+    /// Optional features implemented by the plugin (see PluginCapabilities)
+    ///
+    /// Must stay just after the version, hosts read it before anything else
+    ///
+    pub capabilities: u32,
+
     ///
     ///
     pub name: *const c_char,
@@ -58,6 +95,17 @@ pub struct Plugin {
     /// Return the notifications
     ///
     pub pull_notifications: unsafe extern "C" fn() -> *const c_char,
+
+    ///
+    /// Send a json control request (see ControlRequest) to the runtime
+    /// and return the json response (see ControlResponse)
+    ///
+    pub control: unsafe extern "C" fn(*const c_char) -> *const c_char,
+
+    ///
+    /// Return a json snapshot of the runtime structure (see StructureRegistry)
+    ///
+    pub structure: unsafe extern "C" fn() -> *const c_char,
 }
 
 impl Plugin {
@@ -69,9 +117,12 @@ impl Plugin {
         scan: unsafe extern "C" fn() -> *const c_char,
         produce: unsafe extern "C" fn(*const c_char) -> u32,
         pull_notifications: unsafe extern "C" fn() -> *const c_char,
+        control: unsafe extern "C" fn(*const c_char) -> *const c_char,
+        structure: unsafe extern "C" fn() -> *const c_char,
     ) -> Self {
         Plugin {
             c_interface_version: C_INTERFACE_VERSION,
+            capabilities: 0,
             name: name.as_ptr(),
             version: version.as_ptr(),
             join: join,
@@ -79,9 +130,33 @@ impl Plugin {
             scan: scan,
             produce: produce,
             pull_notifications: pull_notifications,
+            control: control,
+            structure: structure,
         }
     }
 
+    ///
+    /// Announce the optional features implemented by the plugin
+    ///
+    pub fn with_capabilities(mut self, capabilities: PluginCapabilities) -> Self {
+        self.capabilities = capabilities.bits();
+        self
+    }
+
+    ///
+    /// Optional features announced by the plugin, unknown bits are ignored
+    ///
+    pub fn capabilities(&self) -> PluginCapabilities {
+        PluginCapabilities::from_bits_truncate(self.capabilities)
+    }
+
+    ///
+    /// Check that the plugin has been built with the same interface as this crate
+    ///
+    pub fn check_interface_version(&self) -> Result<(), crate::Error> {
+        check_interface_version(self.c_interface_version)
+    }
+
     // /// Converts the ProductionOrder into a C string
     // ///
     // /// Don't forget "".as_c_str().as_ptr()" to use it with the DLL interfaces
@@ -126,3 +201,35 @@ impl Plugin {
         Ok(obj)
     }
 }
+
+///
+/// Check a plugin interface version against the one of this crate
+///
+/// The major must be the same, the minor of the plugin can be older than the one
+/// of the host because minor versions only add optional features.
+///
+pub fn check_interface_version(version: u32) -> Result<(), crate::Error> {
+    let major = version >> 16;
+    let minor = version & 0xFFFF;
+    if major != C_INTERFACE_VERSION_MAJOR || minor > C_INTERFACE_VERSION_MINOR {
+        return Err(crate::Error::PluginError(format!(
+            "C interface version mismatch (plugin: {}.{}, host: {}.{})",
+            major, minor, C_INTERFACE_VERSION_MAJOR, C_INTERFACE_VERSION_MINOR
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_interface_version() {
+        assert!(check_interface_version(C_INTERFACE_VERSION).is_ok());
+        assert!(check_interface_version(C_INTERFACE_VERSION_MAJOR << 16).is_ok());
+        assert!(check_interface_version(C_INTERFACE_VERSION + 1).is_err());
+        assert!(check_interface_version((C_INTERFACE_VERSION_MAJOR + 1) << 16).is_err());
+        assert!(check_interface_version(4).is_err());
+    }
+}
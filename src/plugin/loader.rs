@@ -0,0 +1,349 @@
+use super::{check_interface_version, Plugin, PluginCapabilities};
+use crate::ControlRequest;
+use crate::ControlResponse;
+use crate::Error;
+use crate::ProductionOrder;
+use crate::SequencedNotification;
+use crate::Store;
+use crate::StructureRegistry;
+use libloading::{Library, Symbol};
+use std::ffi::{c_char, CStr, CString};
+use std::path::{Path, PathBuf};
+
+/// Symbol of the function that returns the C interface version of the plugin
+///
+pub static PLUGIN_INTERFACE_VERSION_SYMBOL: &[u8] = b"plugin_interface_version";
+
+/// Symbol of the plugin entry point
+///
+pub static PLUGIN_ENTRY_POINT_SYMBOL: &[u8] = b"plugin_entry_point";
+
+/// Symbol of the function that returns why the entry point failed
+///
+pub static PLUGIN_ENTRY_ERROR_SYMBOL: &[u8] = b"plugin_entry_error";
+
+/// Signature of 'plugin_interface_version'
+///
+type PluginInterfaceVersionFn = unsafe extern "C" fn() -> u32;
+
+/// Signature of 'plugin_entry_point'
+///
+type PluginEntryPointFn = unsafe extern "C" fn(bool, bool, bool, *const c_char) -> Plugin;
+
+/// Signature of 'plugin_entry_error'
+///
+type PluginEntryErrorFn = unsafe extern "C" fn() -> *const c_char;
+
+/// Options given by the host to the plugins it loads
+///
+#[derive(Debug, Clone)]
+pub struct PluginLoadOptions {
+    /// Plugin logs also go to stdout
+    ///
+    pub enable_stdout: bool,
+
+    /// Enable debug logs in the plugin
+    ///
+    pub debug: bool,
+
+    /// Enable trace logs in the plugin
+    ///
+    pub trace: bool,
+
+    /// Json connection options of the engine (see EngineOptions::from_json_str)
+    ///
+    pub engine_config: Option<String>,
+
+    /// Optional features supported by the host
+    ///
+    pub host_capabilities: PluginCapabilities,
+}
+
+impl Default for PluginLoadOptions {
+    fn default() -> Self {
+        Self {
+            enable_stdout: false,
+            debug: false,
+            trace: false,
+            engine_config: None,
+            host_capabilities: PluginCapabilities::all(),
+        }
+    }
+}
+
+/// Plugin loaded from a dynamic library with its checked interface
+///
+pub struct LoadedPlugin {
+    /// Interface of the plugin
+    ///
+    /// Declared before the library to be dropped before it
+    ///
+    plugin: Plugin,
+
+    /// Features supported by both the plugin and the host
+    ///
+    capabilities: PluginCapabilities,
+
+    /// Path of the library
+    ///
+    path: PathBuf,
+
+    /// Keep the library loaded while the plugin is used
+    ///
+    _library: Library,
+}
+
+impl LoadedPlugin {
+    /// Load the library, check its interface version and call its entry point
+    ///
+    /// The version is checked before calling the entry point, because calling
+    /// a function with a different signature is undefined behaviour.
+    ///
+    /// # Safety
+    ///
+    /// The library must be a panduza plugin, its initialisation code is executed.
+    ///
+    pub unsafe fn load<P: AsRef<Path>>(
+        path: P,
+        options: &PluginLoadOptions,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+
+        //
+        // Load the library
+        let library = Library::new(&path).map_err(|e| {
+            Error::PluginError(format!("Unable to load '{}' ({})", path.display(), e))
+        })?;
+
+        //
+        // Check the interface version before anything else
+        let interface_version: Symbol<PluginInterfaceVersionFn> =
+            library.get(PLUGIN_INTERFACE_VERSION_SYMBOL).map_err(|e| {
+                Error::PluginError(format!(
+                    "'{}' does not provide its interface version, plugin too old ? ({})",
+                    path.display(),
+                    e
+                ))
+            })?;
+        check_interface_version(interface_version())
+            .map_err(|e| Error::PluginError(format!("'{}' {:?}", path.display(), e)))?;
+
+        //
+        // Call the entry point
+        let entry_point: Symbol<PluginEntryPointFn> =
+            library.get(PLUGIN_ENTRY_POINT_SYMBOL).map_err(|e| {
+                Error::PluginError(format!("'{}' has no entry point ({})", path.display(), e))
+            })?;
+        let engine_config =
+            match &options.engine_config {
+                Some(config) => Some(CString::new(config.as_str()).map_err(|e| {
+                    Error::InvalidArgument(format!("Invalid engine config ({:?})", e))
+                })?),
+                None => None,
+            };
+        let plugin = entry_point(
+            options.enable_stdout,
+            options.debug,
+            options.trace,
+            engine_config
+                .as_ref()
+                .map_or(std::ptr::null(), |config| config.as_ptr()),
+        );
+
+        //
+        // Older plugins do not report entry point failures
+        if let Ok(entry_error) = library.get::<PluginEntryErrorFn>(PLUGIN_ENTRY_ERROR_SYMBOL) {
+            if let Some(reason) = json_from_c_str_ptr(entry_error())? {
+                return Err(Error::PluginError(format!(
+                    "'{}' entry point failed ({})",
+                    path.display(),
+                    reason
+                )));
+            }
+        }
+
+        //
+        // Double check with the structure itself
+        plugin
+            .check_interface_version()
+            .map_err(|e| Error::PluginError(format!("'{}' {:?}", path.display(), e)))?;
+
+        //
+        // Negotiate optional features
+        let capabilities = plugin.capabilities() & options.host_capabilities;
+
+        Ok(Self {
+            plugin: plugin,
+            capabilities: capabilities,
+            path: path,
+            _library: library,
+        })
+    }
+
+    /// Raw interface of the plugin
+    ///
+    pub fn plugin(&self) -> &Plugin {
+        &self.plugin
+    }
+
+    /// Path of the library
+    ///
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Name of the plugin
+    ///
+    pub fn name(&self) -> String {
+        unsafe { CStr::from_ptr(self.plugin.name) }
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Version of the plugin
+    ///
+    pub fn version(&self) -> String {
+        unsafe { CStr::from_ptr(self.plugin.version) }
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Features supported by both the plugin and the host
+    ///
+    pub fn capabilities(&self) -> PluginCapabilities {
+        self.capabilities
+    }
+
+    /// Return an error if one of the features is not available
+    ///
+    pub fn require(&self, capabilities: PluginCapabilities) -> Result<(), Error> {
+        let missing = capabilities - self.capabilities;
+        if !missing.is_empty() {
+            return Err(Error::PluginError(format!(
+                "Plugin '{}' does not support {:?}",
+                self.name(),
+                missing
+            )));
+        }
+        Ok(())
+    }
+
+    /// List of the drivers that the plugin can produce
+    ///
+    pub fn store(&self) -> Result<Store, Error> {
+        unsafe { self.plugin.store_as_obj() }
+    }
+
+    /// Ask the plugin to produce an instance
+    ///
+    pub fn produce(&self, order: &ProductionOrder) -> Result<(), Error> {
+        let c_order = order.to_c_string()?;
+        let status = unsafe { (self.plugin.produce)(c_order.as_ptr()) };
+        if status != 0 {
+            return Err(Error::PluginError(format!(
+                "Plugin '{}' failed to produce '{}' (status {})",
+                self.name(),
+                order.name,
+                status
+            )));
+        }
+        Ok(())
+    }
+
+    /// Notifications emitted by the plugin runtime since the last pull
+    ///
+    pub fn pull_notifications(&self) -> Result<Vec<SequencedNotification>, Error> {
+        let json = unsafe { json_from_c_str_ptr((self.plugin.pull_notifications)()) }?;
+        match json {
+            Some(json) => deserialize(&json),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Instances found by the scanners of the plugin
+    ///
+    pub fn scan(&self) -> Result<Vec<ProductionOrder>, Error> {
+        self.require(PluginCapabilities::SCAN)?;
+        let json = unsafe { json_from_c_str_ptr((self.plugin.scan)()) }?;
+        match json {
+            Some(json) => deserialize(&json),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Send a control request to the plugin runtime
+    ///
+    pub fn control(&self, request: &ControlRequest) -> Result<ControlResponse, Error> {
+        self.require(PluginCapabilities::CONTROL)?;
+        let c_request = CString::new(
+            serde_json::to_string(request)
+                .map_err(|e| Error::SerializeFailure(format!("{:?}", e)))?,
+        )
+        .map_err(|e| Error::InternalLogic(format!("Failed to build CString ({:?})", e)))?;
+        let json = unsafe { json_from_c_str_ptr((self.plugin.control)(c_request.as_ptr())) }?;
+        match json {
+            Some(json) => deserialize(&json),
+            None => Err(Error::PluginError(format!(
+                "Plugin '{}' gave no control response",
+                self.name()
+            ))),
+        }
+    }
+
+    /// Snapshot of the instances, classes and attributes of the plugin runtime
+    ///
+    pub fn structure(&self) -> Result<StructureRegistry, Error> {
+        self.require(PluginCapabilities::STRUCTURE)?;
+        let json = unsafe { json_from_c_str_ptr((self.plugin.structure)()) }?;
+        match json {
+            Some(json) => deserialize(&json),
+            None => Err(Error::PluginError(format!(
+                "Plugin '{}' gave no structure",
+                self.name()
+            ))),
+        }
+    }
+
+    /// Stop all the instances of the plugin and wait for the end of its runtime
+    ///
+    pub fn shutdown(&self) -> Result<(), Error> {
+        self.require(PluginCapabilities::SHUTDOWN)?;
+        match self.control(&ControlRequest::Shutdown)? {
+            ControlResponse::Error(e) => {
+                return Err(Error::PluginError(format!(
+                    "Plugin '{}' refused to shutdown ({})",
+                    self.name(),
+                    e
+                )))
+            }
+            _ => {}
+        }
+        self.join();
+        Ok(())
+    }
+
+    /// Wait for the end of the plugin runtime thread
+    ///
+    pub fn join(&self) {
+        unsafe { (self.plugin.join)() }
+    }
+}
+
+/// Copy the json string given by a plugin, None for a null pointer
+///
+unsafe fn json_from_c_str_ptr(c_str: *const c_char) -> Result<Option<String>, Error> {
+    if c_str.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(c_str)
+        .to_str()
+        .map(|s| Some(s.to_string()))
+        .map_err(|e| Error::InvalidArgument(format!("Invalid C string: {:?}", e)))
+}
+
+/// Deserialize a json string given by a plugin
+///
+fn deserialize<T: serde::de::DeserializeOwned>(json: &str) -> Result<T, Error> {
+    serde_json::from_str(json)
+        .map_err(|e| Error::DeserializeError(format!("Failed to deserialize JSON: {:?}", e)))
+}
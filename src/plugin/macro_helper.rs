@@ -2,6 +2,9 @@
 macro_rules! plugin_interface {
     ($plg_name:literal, $plg_version:literal) => {
         use panduza_platform_core::new_engine;
+        use panduza_platform_core::ControlCommand;
+        use panduza_platform_core::ControlRequest;
+        use panduza_platform_core::ControlResponse;
         use panduza_platform_core::Engine;
         use panduza_platform_core::EngineBuilder;
         use panduza_platform_core::EngineOptions;
@@ -9,9 +12,12 @@ macro_rules! plugin_interface {
         use panduza_platform_core::Logger;
         use panduza_platform_core::NotificationGroup;
         use panduza_platform_core::Plugin;
+        use panduza_platform_core::PluginCapabilities;
         use panduza_platform_core::ProductionOrder;
         use panduza_platform_core::Runtime;
         use panduza_platform_core::RuntimeBuilder;
+        use panduza_platform_core::ScanMachine;
+        use panduza_platform_core::StructureRegistry;
         use std::ffi::c_char;
         use std::ffi::CString;
         use std::thread;
@@ -35,6 +41,10 @@ macro_rules! plugin_interface {
         ///
         static mut PLG_VERSION: Option<CString> = None;
 
+        /// Reason why the entry point failed, the runtime is not started when set
+        ///
+        static mut PLG_ENTRY_ERROR: Option<CString> = None;
+
         /// Object of the local factory
         ///
         static mut FACTORY: Option<Factory> = None;
@@ -43,6 +53,10 @@ macro_rules! plugin_interface {
         ///
         static mut FACTORY_STORE: Option<CString> = None;
 
+        /// Options of the engine, given by the platform in the entry point
+        ///
+        static mut ENGINE_OPTIONS: Option<EngineOptions> = None;
+
         /// Handle of the thread
         ///
         static mut THREAD_HANDLE: Option<JoinHandle<()>> = None;
@@ -51,14 +65,32 @@ macro_rules! plugin_interface {
         ///
         static mut POS: Option<tokio::sync::mpsc::Sender<ProductionOrder>> = None;
 
+        /// Control Command Sender
+        ///
+        static mut CONTROL_SENDER: Option<tokio::sync::mpsc::Sender<ControlCommand>> = None;
+
+        /// Last control response, keep it here to maintain pointer validity
+        ///
+        static mut CONTROL_RESPONSE: Option<CString> = None;
+
         /// Group of notifications to be send to the platform
         ///
         static mut RUNTIME_NOTIFICATIONS_GROUP: Option<
             std::sync::Arc<std::sync::Mutex<NotificationGroup>>,
         > = None;
 
-        // static mut SCAN_MACHINE: Option<ScanMachine> = None;
-        // static mut FACTORY_SCAN_RESULT: Option<CString> = None;
+        /// Structure of the runtime, for resync of the platform
+        ///
+        static mut RUNTIME_STRUCTURE: Option<std::sync::Arc<std::sync::Mutex<StructureRegistry>>> =
+            None;
+
+        /// Scanners provided by the producers
+        ///
+        static mut SCAN_MACHINE: Option<ScanMachine> = None;
+
+        /// Last scan result, keep it here to maintain pointer validity
+        ///
+        static mut FACTORY_SCAN_RESULT: Option<CString> = None;
 
         ///
         /// Main Entry Point for the plugin runtime
@@ -75,8 +107,8 @@ macro_rules! plugin_interface {
         ///
         unsafe fn start_runtime() {
             //
-            // Already started
-            if RUNTIME_STARTED {
+            // Already started or not allowed to start
+            if RUNTIME_STARTED || PLG_ENTRY_ERROR.is_some() {
                 return;
             }
 
@@ -86,7 +118,7 @@ macro_rules! plugin_interface {
 
             //
             //
-            let engine_options = EngineOptions::new("127.0.0.1", 7447, "minica.pem", None);
+            let engine_options = ENGINE_OPTIONS.take().unwrap_or_default();
             let engine_builder = EngineBuilder::new(engine_options);
 
             //
@@ -98,10 +130,18 @@ macro_rules! plugin_interface {
             //
             RUNTIME_NOTIFICATIONS_GROUP = Some(runtime_builder.clone_notifications());
 
+            //
+            //
+            RUNTIME_STRUCTURE = Some(runtime_builder.clone_structure());
+
             //
             //
             POS = Some(runtime_prod_order);
 
+            //
+            //
+            CONTROL_SENDER = Some(runtime_builder.control_sender());
+
             //
             // Start thread
             let __handle: JoinHandle<()> = thread::spawn(move || {
@@ -118,7 +158,11 @@ macro_rules! plugin_interface {
         /// Plugin management only, join the worker thread in platform
         ///
         pub unsafe extern "C" fn join() {
-            THREAD_HANDLE.take().unwrap().join().unwrap();
+            //
+            // No thread when the entry point failed
+            if let Some(handle) = THREAD_HANDLE.take() {
+                handle.join().unwrap();
+            }
         }
 
         ///
@@ -134,19 +178,27 @@ macro_rules! plugin_interface {
         /// Scan the server and try to find connected devices instances
         ///
         pub unsafe extern "C" fn scan() -> *const c_char {
-            // LOGGER.as_ref().unwrap().trace(format!("scan !"));
-
-            // //
-            // // Start scan
-            // unsafe {
-            //     FACTORY_SCAN_RESULT =
-            //         Some(SCAN_MACHINE.as_ref().unwrap().scan_as_c_string().unwrap());
-            // }
-
-            // //
-            // // Put the result available to the platform
-            // FACTORY_SCAN_RESULT.as_ref().unwrap().as_c_str().as_ptr()
-            return std::ptr::null();
+            LOGGER.as_ref().unwrap().trace(format!("scan !"));
+
+            //
+            // Start scan
+            FACTORY_SCAN_RESULT = match SCAN_MACHINE.as_ref().unwrap().scan_as_c_string() {
+                Ok(result) => Some(result),
+                Err(e) => {
+                    LOGGER
+                        .as_ref()
+                        .unwrap()
+                        .error(format!("scan failure {:?}", e));
+                    None
+                }
+            };
+
+            //
+            // Put the result available to the platform
+            match FACTORY_SCAN_RESULT.as_ref() {
+                Some(result) => result.as_c_str().as_ptr(),
+                None => std::ptr::null(),
+            }
         }
 
         ///
@@ -157,6 +209,16 @@ macro_rules! plugin_interface {
             // Debug log
             LOGGER.as_ref().unwrap().trace("produce");
 
+            //
+            // The runtime cannot start after a failed entry point
+            if PLG_ENTRY_ERROR.is_some() {
+                LOGGER
+                    .as_ref()
+                    .unwrap()
+                    .error("produce refused, the entry point failed");
+                return 1;
+            }
+
             //
             // Start runtime if not already
             start_runtime();
@@ -196,13 +258,82 @@ macro_rules! plugin_interface {
             // return std::ptr::null();
         }
 
+        ///
+        /// Snapshot of the runtime structure
+        ///
+        pub unsafe extern "C" fn structure() -> *const c_char {
+            match &RUNTIME_STRUCTURE {
+                Some(structure) => structure.lock().unwrap().snapshot_and_serialize(),
+                None => {
+                    LOGGER
+                        .as_ref()
+                        .unwrap()
+                        .error("RUNTIME_STRUCTURE is 'None'");
+                    std::ptr::null()
+                }
+            }
+        }
+
+        ///
+        /// Send a control request to the runtime and wait for its response
+        ///
+        pub unsafe extern "C" fn control(str_request: *const c_char) -> *const c_char {
+            //
+            // Debug log
+            LOGGER.as_ref().unwrap().trace("control");
+
+            //
+            // Parse, forward and wait for the runtime
+            let response = match ControlRequest::from_c_str_ptr(str_request) {
+                Ok(request) => {
+                    ControlCommand::blocking_request(CONTROL_SENDER.as_ref().unwrap(), request)
+                }
+                Err(e) => ControlResponse::Error(format!("{:?}", e)),
+            };
+
+            //
+            // Put the response available to the platform
+            CONTROL_RESPONSE = response.to_c_string().ok();
+            match CONTROL_RESPONSE.as_ref() {
+                Some(c_string) => c_string.as_c_str().as_ptr(),
+                None => std::ptr::null(),
+            }
+        }
+
+        /// Version of the C interface used by this plugin
+        ///
+        /// Hosts check it before calling the entry point
+        ///
+        #[no_mangle]
+        pub extern "C" fn plugin_interface_version() -> u32 {
+            panduza_platform_core::plugin::C_INTERFACE_VERSION
+        }
+
+        /// Reason why the entry point failed, null if it succeeded
+        ///
+        /// Hosts check it after calling the entry point
+        ///
+        #[no_mangle]
+        pub unsafe extern "C" fn plugin_entry_error() -> *const c_char {
+            match PLG_ENTRY_ERROR.as_ref() {
+                Some(c_string) => c_string.as_c_str().as_ptr(),
+                None => std::ptr::null(),
+            }
+        }
+
         /// Entry Point of the plugin
         ///
+        /// 'engine_config' is a json string with the connection options of the engine
+        /// (see EngineOptions::from_json_str), null to use the default options.
+        /// An invalid config fails the entry point (see plugin_entry_error), the
+        /// runtime is not started and the plugin announces no capabilities.
+        ///
         #[no_mangle]
         pub unsafe extern "C" fn plugin_entry_point(
             enable_stdout: bool,
             debug: bool,
             trace: bool,
+            engine_config: *const c_char,
         ) -> Plugin {
             //
             // Create a static reference for the plugin name
@@ -220,12 +351,18 @@ macro_rules! plugin_interface {
             logger.info("plugin_entry_point");
             LOGGER = Some(logger);
 
-            // //
-            // let mut scan_machine = ScanMachine::new();
-            // scan_machine.add_scanners(plugin_scanners());
-            // unsafe {
-            //     SCAN_MACHINE = Some(scan_machine);
-            // }
+            //
+            // Engine connection options from the platform
+            PLG_ENTRY_ERROR = None;
+            ENGINE_OPTIONS = match EngineOptions::from_c_str_ptr(engine_config) {
+                Ok(options) => Some(options),
+                Err(e) => {
+                    let message = format!("bad engine config {:?}", e);
+                    LOGGER.as_ref().unwrap().error(message.clone());
+                    PLG_ENTRY_ERROR = Some(CString::new(message.replace('\0', "")).unwrap());
+                    None
+                }
+            };
 
             // if factory none
             // init factory
@@ -233,6 +370,7 @@ macro_rules! plugin_interface {
             factory.add_producers(plugin_producers());
             unsafe {
                 FACTORY_STORE = Some(factory.store_as_c_string().unwrap());
+                SCAN_MACHINE = Some(factory.scan_machine());
                 FACTORY = Some(factory);
             }
 
@@ -242,6 +380,20 @@ macro_rules! plugin_interface {
 
             //
             // Build the plugin object
+            let mut capabilities = match PLG_ENTRY_ERROR {
+                Some(_) => PluginCapabilities::empty(),
+                None => {
+                    PluginCapabilities::CONTROL
+                        | PluginCapabilities::ENGINE_CONFIG
+                        | PluginCapabilities::SHUTDOWN
+                        | PluginCapabilities::STRUCTURE
+                }
+            };
+            //
+            // Scan only if a producer provides a scanner
+            if PLG_ENTRY_ERROR.is_none() && !SCAN_MACHINE.as_ref().unwrap().is_empty() {
+                capabilities |= PluginCapabilities::SCAN;
+            }
             let p = Plugin::new(
                 PLG_NAME.as_ref().unwrap().as_c_str(),
                 PLG_VERSION.as_ref().unwrap().as_c_str(),
@@ -250,7 +402,10 @@ macro_rules! plugin_interface {
                 scan,
                 produce,
                 pull_notifications,
-            );
+                control,
+                structure,
+            )
+            .with_capabilities(capabilities);
             return p;
         }
     };
@@ -0,0 +1,520 @@
+use super::loader::{LoadedPlugin, PluginLoadOptions};
+use crate::env::{
+    system_default_plugins_dir, system_dyn_lib_extension, system_dyn_lib_prefix,
+    system_plugins_dir_paths,
+};
+use crate::log_debug;
+use crate::log_error;
+use crate::log_info;
+use crate::log_warn;
+use crate::ControlRequest;
+use crate::ControlResponse;
+use crate::Error;
+use crate::Logger;
+use crate::ProductionOrder;
+use crate::SequencedNotification;
+use crate::Store;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Load the plugins of the system and route the requests of the platform to them
+///
+pub struct PluginManager {
+    /// Logger of the manager
+    ///
+    logger: Logger,
+
+    /// Options given to each loaded plugin
+    ///
+    options: PluginLoadOptions,
+
+    /// Loaded plugins
+    ///
+    plugins: Vec<LoadedPlugin>,
+
+    /// Merged stores of all the plugins
+    ///
+    store: Store,
+
+    /// Index of the plugin that owns each dref
+    ///
+    dref_owners: HashMap<String, usize>,
+
+    /// Production orders sent to the plugins, by instance name
+    ///
+    /// Used to produce the instances again after a plugin reload
+    ///
+    produced: Mutex<HashMap<String, ProductionOrder>>,
+
+    /// Library installed for each plugin, by plugin name
+    ///
+    /// The file a plugin has been loaded from is a copy after a reload
+    ///
+    sources: HashMap<String, PathBuf>,
+
+    /// Number of library copies made by the reloads
+    ///
+    reload_count: u64,
+}
+
+impl PluginManager {
+    /// Create a manager without any plugin
+    ///
+    pub fn new(options: PluginLoadOptions) -> Self {
+        Self {
+            logger: Logger::new_for_platform_service("plugins"),
+            options: options,
+            plugins: Vec::new(),
+            store: Store::default(),
+            dref_owners: HashMap::new(),
+            produced: Mutex::new(HashMap::new()),
+            sources: HashMap::new(),
+            reload_count: 0,
+        }
+    }
+
+    /// Load all the plugins found in the system plugin directories
+    ///
+    /// A plugin that fails to load is logged and skipped. Return the number of loaded plugins.
+    ///
+    /// # Safety
+    ///
+    /// See `LoadedPlugin::load`, the initialisation code of each library is executed.
+    ///
+    pub unsafe fn load_system_plugins(&mut self) -> usize {
+        let mut count = 0;
+        for dir in system_plugins_dir_paths() {
+            for path in Self::find_plugin_files(&dir) {
+                match self.load_plugin(&path) {
+                    Ok(_) => count += 1,
+                    Err(e) => log_error!(self.logger, "Skip plugin {:?}", e),
+                }
+            }
+        }
+        count
+    }
+
+    /// Load one plugin and register the drivers it provides
+    ///
+    /// A dref already provided by a loaded plugin stays with this first plugin.
+    ///
+    /// # Safety
+    ///
+    /// See `LoadedPlugin::load`
+    ///
+    pub unsafe fn load_plugin<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let plugin = LoadedPlugin::load(path, &self.options)?;
+        if self.plugins.iter().any(|p| p.name() == plugin.name()) {
+            return Err(Error::PluginError(format!(
+                "Plugin '{}' already loaded, '{}' ignored",
+                plugin.name(),
+                plugin.path().display()
+            )));
+        }
+        let store = plugin.store()?;
+        self.register_store(self.plugins.len(), &plugin.name(), &store);
+        self.sources
+            .insert(plugin.name(), plugin.path().to_path_buf());
+
+        log_info!(
+            self.logger,
+            "Plugin '{}' {} loaded from '{}'",
+            plugin.name(),
+            plugin.version(),
+            plugin.path().display()
+        );
+        self.plugins.push(plugin);
+        Ok(())
+    }
+
+    /// Replace a loaded plugin by a new load of its library
+    ///
+    /// If 'new_version_dir' is given, the library with the same file name found in
+    /// this directory is loaded and then installed into the system plugin directory.
+    /// The library is always loaded from a fresh copy, so the file mapped by the
+    /// running plugin is never modified and the new load is not the old one.
+    ///
+    /// The old plugin keeps running until the new one has loaded, a failure before
+    /// this point leaves it untouched. The instances it still manages are then
+    /// stopped and produced again with the new plugin from their production orders.
+    ///
+    /// # Safety
+    ///
+    /// See `LoadedPlugin::load`
+    ///
+    pub unsafe fn reload_plugin(
+        &mut self,
+        name: &str,
+        new_version_dir: Option<String>,
+    ) -> Result<(), Error> {
+        let index = self
+            .plugins
+            .iter()
+            .position(|p| p.name() == name)
+            .ok_or(Error::PluginError(format!("No plugin named '{}'", name)))?;
+        let installed = self
+            .sources
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| self.plugins[index].path().to_path_buf());
+        let file_name = installed
+            .file_name()
+            .ok_or(Error::PluginError(format!(
+                "Bad plugin path '{}'",
+                installed.display()
+            )))?
+            .to_owned();
+        let source = match &new_version_dir {
+            Some(dir) => PathBuf::from(dir).join(&file_name),
+            None => installed.clone(),
+        };
+
+        //
+        // Load the new version beside the old one
+        let copy = self.copy_for_load(&source)?;
+        let plugin = match LoadedPlugin::load(&copy, &self.options) {
+            Ok(plugin) => plugin,
+            Err(e) => {
+                let _ = std::fs::remove_file(&copy);
+                return Err(e);
+            }
+        };
+        if plugin.name() != name {
+            let found = plugin.name();
+            self.discard(plugin);
+            return Err(Error::PluginError(format!(
+                "'{}' provides the plugin '{}' instead of '{}'",
+                source.display(),
+                found,
+                name
+            )));
+        }
+
+        //
+        // Install the new version for the next starts of the platform
+        let installed = match &new_version_dir {
+            Some(_) => {
+                match system_default_plugins_dir()
+                    .map_err(|e| Error::PluginError(format!("{:?}", e)))
+                    .and_then(|dir| Self::install_library(&source, &dir.join(&file_name)))
+                {
+                    Ok(installed) => installed,
+                    Err(e) => {
+                        self.discard(plugin);
+                        return Err(e);
+                    }
+                }
+            }
+            None => installed,
+        };
+
+        //
+        // Keep only the orders of the instances still managed by the old plugin
+        let live = match self.plugins[index].control(&ControlRequest::List) {
+            Ok(ControlResponse::Instances(names)) => names,
+            Ok(response) => {
+                self.discard(plugin);
+                return Err(Error::PluginError(format!(
+                    "Plugin '{}' did not list its instances ({:?})",
+                    name, response
+                )));
+            }
+            Err(e) => {
+                self.discard(plugin);
+                return Err(e);
+            }
+        };
+        let orders: Vec<ProductionOrder> = {
+            let mut produced = self.produced.lock().unwrap();
+            produced.retain(|instance, order| {
+                self.dref_owners.get(order.dref()) != Some(&index) || live.contains(instance)
+            });
+            produced
+                .values()
+                .filter(|order| self.dref_owners.get(order.dref()) == Some(&index))
+                .cloned()
+                .collect()
+        };
+
+        //
+        // Stop the runtime of the old plugin and all its instances
+        if let Err(e) = self.plugins[index].shutdown() {
+            self.discard(plugin);
+            return Err(e);
+        }
+        log_info!(self.logger, "Plugin '{}' stopped", name);
+
+        //
+        // Swap the plugins, the old library is unloaded here
+        let old = std::mem::replace(&mut self.plugins[index], plugin);
+        let old_path = old.path().to_path_buf();
+        drop(old);
+        if old_path.starts_with(Self::reload_dir()) {
+            let _ = std::fs::remove_file(&old_path);
+        }
+        self.sources.insert(name.to_string(), installed);
+        self.rebuild_registry()?;
+        log_info!(
+            self.logger,
+            "Plugin '{}' {} reloaded from '{}'",
+            name,
+            self.plugins[index].version(),
+            source.display()
+        );
+
+        //
+        // Produce again the instances
+        for order in orders {
+            if let Err(e) = self.produce(&order) {
+                self.produced.lock().unwrap().remove(&order.name);
+                log_error!(self.logger, "Cannot produce '{}' again {:?}", order.name, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Loaded plugins
+    ///
+    pub fn plugins(&self) -> &Vec<LoadedPlugin> {
+        &self.plugins
+    }
+
+    /// Drivers provided by all the plugins
+    ///
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Plugin that provides the given dref
+    ///
+    pub fn plugin_for_dref(&self, dref: &str) -> Option<&LoadedPlugin> {
+        self.dref_owners
+            .get(dref)
+            .map(|index| &self.plugins[*index])
+    }
+
+    /// Send the production order to the plugin that owns its dref
+    ///
+    pub fn produce(&self, order: &ProductionOrder) -> Result<(), Error> {
+        let plugin = self
+            .plugin_for_dref(order.dref())
+            .ok_or(Error::InvalidArgument(format!(
+                "No plugin provides '{}' for '{}'",
+                order.dref(),
+                order.name
+            )))?;
+        log_debug!(
+            self.logger,
+            "Produce '{}' with plugin '{}'",
+            order.name,
+            plugin.name()
+        );
+        plugin.produce(order)?;
+        self.produced
+            .lock()
+            .unwrap()
+            .insert(order.name.clone(), order.clone());
+        Ok(())
+    }
+
+    /// Pull the notifications of all the plugins, with the name of their plugin
+    ///
+    /// Sequence numbers are counted per plugin.
+    ///
+    pub fn pull_notifications(&self) -> Vec<(String, SequencedNotification)> {
+        let mut notifications = Vec::new();
+        for plugin in self.plugins.iter() {
+            match plugin.pull_notifications() {
+                Ok(pulled) => {
+                    let name = plugin.name();
+                    notifications.extend(pulled.into_iter().map(|n| (name.clone(), n)));
+                }
+                Err(e) => log_error!(
+                    self.logger,
+                    "Failed to pull notifications of '{}' {:?}",
+                    plugin.name(),
+                    e
+                ),
+            }
+        }
+        notifications
+    }
+
+    /// Scan the instances with all the plugins that support it
+    ///
+    pub fn scan(&self) -> Vec<ProductionOrder> {
+        let mut orders = Vec::new();
+        for plugin in self.plugins.iter() {
+            match plugin.scan() {
+                Ok(mut found) => orders.append(&mut found),
+                Err(e) => log_debug!(self.logger, "No scan for '{}' {:?}", plugin.name(), e),
+            }
+        }
+        orders
+    }
+
+    /// Wait for the end of all the plugin runtimes
+    ///
+    pub fn join(&self) {
+        for plugin in self.plugins.iter() {
+            plugin.join();
+        }
+    }
+
+    /// Give the drefs of the store to the plugin at 'index', except the ones already owned
+    ///
+    fn register_store(&mut self, index: usize, plugin_name: &str, store: &Store) {
+        for dref in store.products.keys() {
+            match self.dref_owners.get(dref) {
+                Some(owner) => log_warn!(
+                    self.logger,
+                    "'{}' from '{}' already provided by '{}', ignored",
+                    dref,
+                    plugin_name,
+                    self.plugins[*owner].name()
+                ),
+                None => {
+                    self.dref_owners.insert(dref.clone(), index);
+                }
+            }
+        }
+
+        //
+        // Keep the products of the first owner in the merged store
+        let mut new_products = Store::default();
+        for (dref, product) in store.products.iter() {
+            if self.dref_owners.get(dref) == Some(&index) {
+                new_products.products.insert(dref.clone(), product.clone());
+            }
+        }
+        self.store.extend_by_copy(&new_products);
+    }
+
+    /// Compute again the merged store and the dref owners from the loaded plugins
+    ///
+    fn rebuild_registry(&mut self) -> Result<(), Error> {
+        self.store = Store::default();
+        self.dref_owners.clear();
+        let mut stores = Vec::new();
+        for plugin in self.plugins.iter() {
+            stores.push((plugin.name(), plugin.store()?));
+        }
+        for (index, (name, store)) in stores.iter().enumerate() {
+            self.register_store(index, name, store);
+        }
+        Ok(())
+    }
+
+    /// Stop a plugin that has been loaded but will not be used
+    ///
+    /// The library stays loaded if its runtime cannot be stopped.
+    ///
+    fn discard(&self, plugin: LoadedPlugin) {
+        if let Err(e) = plugin.shutdown() {
+            log_warn!(
+                self.logger,
+                "Discarded plugin '{}' not stopped, keep it loaded {:?}",
+                plugin.name(),
+                e
+            );
+            std::mem::forget(plugin);
+            return;
+        }
+        let path = plugin.path().to_path_buf();
+        drop(plugin);
+        let _ = std::fs::remove_file(path);
+    }
+
+    /// Directory of the library copies loaded by the reloads
+    ///
+    fn reload_dir() -> PathBuf {
+        std::env::temp_dir().join("panduza-plugins")
+    }
+
+    /// Copy a library to a new path of the reload directory
+    ///
+    /// A library is loaded only once per path, a new path gives a new load.
+    ///
+    fn copy_for_load(&mut self, source: &Path) -> Result<PathBuf, Error> {
+        let dir = Self::reload_dir();
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            Error::PluginError(format!("Cannot create '{}' ({})", dir.display(), e))
+        })?;
+        let stem = source
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or(Error::PluginError(format!(
+                "Bad plugin path '{}'",
+                source.display()
+            )))?;
+        self.reload_count += 1;
+        let mut file_name = format!("{}-{}-{}", stem, std::process::id(), self.reload_count);
+        if let Some(extension) = source.extension().and_then(|e| e.to_str()) {
+            file_name = format!("{}.{}", file_name, extension);
+        }
+        let copy = dir.join(file_name);
+        std::fs::copy(source, &copy).map_err(|e| {
+            Error::PluginError(format!(
+                "Cannot copy '{}' into '{}' ({})",
+                source.display(),
+                copy.display(),
+                e
+            ))
+        })?;
+        Ok(copy)
+    }
+
+    /// Install a library at 'destination' without writing into the existing file
+    ///
+    /// The library is copied beside the destination and then renamed over it,
+    /// a running plugin keeps its mapping of the old file.
+    ///
+    fn install_library(source: &Path, destination: &Path) -> Result<PathBuf, Error> {
+        let install_error = |e: std::io::Error| {
+            Error::PluginError(format!(
+                "Cannot install '{}' into '{}' ({})",
+                source.display(),
+                destination.display(),
+                e
+            ))
+        };
+        if let Some(dir) = destination.parent() {
+            std::fs::create_dir_all(dir).map_err(install_error)?;
+        }
+        let mut temporary = destination.as_os_str().to_owned();
+        temporary.push(".new");
+        let temporary = PathBuf::from(temporary);
+        std::fs::copy(source, &temporary).map_err(install_error)?;
+        std::fs::rename(&temporary, destination).map_err(|e| {
+            let _ = std::fs::remove_file(&temporary);
+            install_error(e)
+        })?;
+        Ok(destination.to_path_buf())
+    }
+
+    /// Files of a directory that look like plugins for this system
+    ///
+    fn find_plugin_files(dir: &Path) -> Vec<PathBuf> {
+        let prefix = system_dyn_lib_prefix().unwrap_or_default();
+        let extension = system_dyn_lib_extension().unwrap_or_default();
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path.extension().and_then(|e| e.to_str()) == Some(extension.as_str())
+                    && path
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .map_or(false, |n| n.starts_with(&prefix))
+            })
+            .collect();
+        files.sort();
+        files
+    }
+}
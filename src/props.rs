@@ -1,3 +1,4 @@
+use crate::InstanceSettings;
 use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{Map, Value as JsonValue};
@@ -16,6 +17,34 @@ pub enum PropType {
     Object,
 }
 
+impl PropType {
+    ///
+    /// Name of the type in JSON Schema
+    ///
+    pub fn json_schema_type(&self) -> &'static str {
+        match self {
+            PropType::Bool => "boolean",
+            PropType::Number => "number",
+            PropType::String => "string",
+            PropType::Array => "array",
+            PropType::Object => "object",
+        }
+    }
+
+    ///
+    /// Check if the json value matches this type
+    ///
+    pub fn matches(&self, value: &JsonValue) -> bool {
+        match self {
+            PropType::Bool => value.is_boolean(),
+            PropType::Number => value.is_number(),
+            PropType::String => value.is_string(),
+            PropType::Array => value.is_array(),
+            PropType::Object => value.is_object(),
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 ///
 ///
@@ -35,6 +64,61 @@ pub struct Prop {
     ///
     ///
     pub default: JsonValue,
+
+    ///
+    /// Minimal value (Number only)
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+
+    ///
+    /// Maximal value (Number only)
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+
+    ///
+    /// Allowed values, any value is allowed if None
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub choices: Option<Vec<JsonValue>>,
+
+    ///
+    /// Unit of the value (V, A, ms...)
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+
+    ///
+    /// True if the user must provide the value, the default is then ignored
+    ///
+    #[serde(default)]
+    pub required: bool,
+
+    ///
+    /// True if the value must be hidden in user interfaces (passwords, keys...)
+    ///
+    #[serde(default)]
+    pub secret: bool,
+
+    ///
+    /// True if the default only documents the prop, the key then stays absent
+    /// from the settings when it is missing
+    ///
+    #[serde(skip)]
+    pub documentation_only: bool,
+
+    ///
+    /// Description of the entries (Object only)
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub properties: Option<Props>,
+
+    ///
+    /// Description of the items (Array only)
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub items: Option<Box<Prop>>,
 }
 
 impl Prop {
@@ -46,6 +130,198 @@ impl Prop {
             description: description.into(),
             r#type: r#type,
             default: default,
+            ..Default::default()
+        }
+    }
+
+    ///
+    /// Set the default, inserted in the settings when the key is missing
+    ///
+    pub fn with_default(mut self, default: JsonValue) -> Self {
+        self.default = default;
+        self
+    }
+
+    ///
+    /// The default only documents the prop, it is not inserted in the settings
+    ///
+    pub fn documentation_only(mut self) -> Self {
+        self.documentation_only = true;
+        self
+    }
+
+    ///
+    /// Set the allowed range
+    ///
+    pub fn with_range(mut self, min: f64, max: f64) -> Self {
+        self.min = Some(min);
+        self.max = Some(max);
+        self
+    }
+
+    ///
+    /// Set the allowed values
+    ///
+    pub fn with_choices(mut self, choices: Vec<JsonValue>) -> Self {
+        self.choices = Some(choices);
+        self
+    }
+
+    ///
+    /// Set the unit of the value
+    ///
+    pub fn with_unit<U: Into<String>>(mut self, unit: U) -> Self {
+        self.unit = Some(unit.into());
+        self
+    }
+
+    ///
+    /// The user must provide the value
+    ///
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    ///
+    /// The value must be hidden in user interfaces
+    ///
+    pub fn secret(mut self) -> Self {
+        self.secret = true;
+        self
+    }
+
+    ///
+    /// Describe the entries of an Object prop
+    ///
+    pub fn with_properties(mut self, properties: Props) -> Self {
+        self.properties = Some(properties);
+        self
+    }
+
+    ///
+    /// Describe the items of an Array prop
+    ///
+    pub fn with_items(mut self, items: Prop) -> Self {
+        self.items = Some(Box::new(items));
+        self
+    }
+
+    ///
+    /// Convert the prop into a JSON Schema (draft 2020-12)
+    ///
+    /// The unit is not a standard keyword, it is exported as 'x-unit'.
+    ///
+    pub fn json_schema(&self) -> JsonValue {
+        let mut schema = Map::new();
+        schema.insert(
+            "type".to_string(),
+            JsonValue::String(self.r#type.json_schema_type().to_string()),
+        );
+        schema.insert(
+            "description".to_string(),
+            JsonValue::String(self.description.clone()),
+        );
+        if !self.default.is_null() {
+            //
+            // Only a default really applied is advertised as such
+            match self.documentation_only {
+                true => schema.insert(
+                    "examples".to_string(),
+                    JsonValue::Array(vec![self.default.clone()]),
+                ),
+                false => schema.insert("default".to_string(), self.default.clone()),
+            };
+        }
+        if let Some(min) = self.min {
+            schema.insert("minimum".to_string(), serde_json::json!(min));
+        }
+        if let Some(max) = self.max {
+            schema.insert("maximum".to_string(), serde_json::json!(max));
+        }
+        if let Some(choices) = &self.choices {
+            schema.insert("enum".to_string(), JsonValue::Array(choices.clone()));
+        }
+        if let Some(unit) = &self.unit {
+            schema.insert("x-unit".to_string(), JsonValue::String(unit.clone()));
+        }
+        if self.secret {
+            schema.insert("writeOnly".to_string(), JsonValue::Bool(true));
+        }
+        if let Some(properties) = &self.properties {
+            if let JsonValue::Object(nested) = properties.json_schema() {
+                schema.extend(nested);
+            }
+        }
+        if let Some(items) = &self.items {
+            schema.insert("items".to_string(), items.json_schema());
+        }
+        JsonValue::Object(schema)
+    }
+
+    ///
+    /// Check the value against this prop, 'path' is the key of the value for messages
+    ///
+    /// Nested objects are completed with their default values.
+    ///
+    fn check(
+        &self,
+        path: &str,
+        value: &mut JsonValue,
+        reject_unknown: bool,
+        issues: &mut Vec<String>,
+    ) {
+        //
+        // Type first, other checks are meaningless with a bad type
+        if !self.r#type.matches(value) {
+            issues.push(format!(
+                "'{}' must be {:?}, got {}",
+                path, self.r#type, value
+            ));
+            return;
+        }
+
+        //
+        // Range
+        if let Some(number) = value.as_f64() {
+            if self.min.map_or(false, |min| number < min)
+                || self.max.map_or(false, |max| number > max)
+            {
+                issues.push(format!(
+                    "'{}' must be in [{}, {}], got {}",
+                    path,
+                    self.min.map_or("-inf".to_string(), |v| v.to_string()),
+                    self.max.map_or("+inf".to_string(), |v| v.to_string()),
+                    number
+                ));
+            }
+        }
+
+        //
+        // Choices
+        if let Some(choices) = &self.choices {
+            if !choices.contains(value) {
+                issues.push(format!(
+                    "'{}' must be one of {}, got {}",
+                    path,
+                    JsonValue::Array(choices.clone()),
+                    value
+                ));
+            }
+        }
+
+        //
+        // Nested object
+        if let (Some(properties), Some(map)) = (&self.properties, value.as_object_mut()) {
+            properties.check(path, map, reject_unknown, issues);
+        }
+
+        //
+        // Array items
+        if let (Some(items), Some(array)) = (&self.items, value.as_array_mut()) {
+            for (i, item) in array.iter_mut().enumerate() {
+                items.check(&format!("{}[{}]", path, i), item, reject_unknown, issues);
+            }
         }
     }
 }
@@ -145,7 +421,7 @@ impl Props {
             name,
             description,
             PropType::Number,
-            serde_json::json!(default.into()),
+            number_value(default.into()),
         );
     }
 
@@ -160,6 +436,246 @@ impl Props {
     ) {
         self.add_entry(name, description, PropType::Bool, JsonValue::Bool(default));
     }
+
+    ///
+    /// Add a prop built with the Prop helpers (with_range, required...)
+    ///
+    pub fn add_prop<A: Into<String>>(&mut self, name: A, prop: Prop) {
+        self.entries.insert(name.into(), prop);
+    }
+
+    ///
+    ///
+    ///
+    pub fn add_ranged_number_prop<
+        A: Into<String>,
+        B: Into<String>,
+        C: Into<f64>,
+        U: Into<String>,
+    >(
+        &mut self,
+        name: A,
+        description: B,
+        default: C,
+        min: f64,
+        max: f64,
+        unit: U,
+    ) {
+        self.add_prop(
+            name,
+            Prop::new(description, PropType::Number, number_value(default.into()))
+                .with_range(min, max)
+                .with_unit(unit),
+        );
+    }
+
+    ///
+    ///
+    ///
+    pub fn add_enum_prop<A: Into<String>, B: Into<String>, C: Into<String>, S: Into<String>>(
+        &mut self,
+        name: A,
+        description: B,
+        default: C,
+        choices: Vec<S>,
+    ) {
+        self.add_prop(
+            name,
+            Prop::new(
+                description,
+                PropType::String,
+                JsonValue::String(default.into()),
+            )
+            .with_choices(
+                choices
+                    .into_iter()
+                    .map(|c| JsonValue::String(c.into()))
+                    .collect(),
+            ),
+        );
+    }
+
+    ///
+    ///
+    ///
+    pub fn add_object_prop<A: Into<String>, B: Into<String>>(
+        &mut self,
+        name: A,
+        description: B,
+        properties: Props,
+    ) {
+        self.add_prop(
+            name,
+            Prop::new(description, PropType::Object, JsonValue::Null).with_properties(properties),
+        );
+    }
+
+    ///
+    ///
+    ///
+    pub fn add_array_prop<A: Into<String>, B: Into<String>>(
+        &mut self,
+        name: A,
+        description: B,
+        items: Prop,
+        default: Vec<JsonValue>,
+    ) {
+        self.add_prop(
+            name,
+            Prop::new(description, PropType::Array, JsonValue::Array(default)).with_items(items),
+        );
+    }
+
+    ///
+    /// Convert the props into the JSON Schema (draft 2020-12) of an object
+    ///
+    pub fn json_schema(&self) -> JsonValue {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for (name, prop) in self.iter_sorted() {
+            properties.insert(name.clone(), prop.json_schema());
+            if prop.required {
+                required.push(JsonValue::String(name.clone()));
+            }
+        }
+        serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    }
+
+    ///
+    /// Get a prop from its name
+    ///
+    pub fn get(&self, name: &str) -> Option<&Prop> {
+        self.entries.get(name)
+    }
+
+    ///
+    /// Iterate over the props sorted by name
+    ///
+    pub fn iter_sorted(&self) -> Vec<(&String, &Prop)> {
+        let mut entries: Vec<(&String, &Prop)> = self.entries.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        entries
+    }
+
+    ///
+    /// Keys of the settings that are not described by a prop
+    ///
+    pub fn unknown_keys(&self, settings: &Option<InstanceSettings>) -> Vec<String> {
+        let mut keys = Vec::new();
+        if let Some(JsonValue::Object(map)) = settings {
+            for key in map.keys() {
+                if !self.entries.contains_key(key) {
+                    keys.push(key.clone());
+                }
+            }
+        }
+        keys.sort();
+        keys
+    }
+
+    ///
+    /// Check the settings against the props and fill in the declared defaults of the missing keys
+    ///
+    /// The defaults marked with `Prop::documentation_only` are not inserted, those
+    /// missing optional keys stay absent.
+    ///
+    /// Return the completed settings or a `BadSettings` error that lists every
+    /// offending key. Unknown keys are kept, unless 'reject_unknown' is set.
+    ///
+    pub fn validate(
+        &self,
+        settings: Option<InstanceSettings>,
+        reject_unknown: bool,
+    ) -> Result<InstanceSettings, crate::Error> {
+        //
+        // No settings means empty settings
+        let mut map = match settings {
+            None | Some(JsonValue::Null) => Map::new(),
+            Some(JsonValue::Object(map)) => map,
+            Some(other) => {
+                return Err(crate::Error::BadSettings(format!(
+                    "settings must be an object, got {}",
+                    other
+                )))
+            }
+        };
+
+        //
+        // Collect all the issues
+        let mut issues = Vec::new();
+        self.check("", &mut map, reject_unknown, &mut issues);
+
+        //
+        // Report every offending key at once
+        if issues.is_empty() {
+            Ok(JsonValue::Object(map))
+        } else {
+            Err(crate::Error::BadSettings(issues.join("; ")))
+        }
+    }
+
+    ///
+    /// Check an object against the props, 'path' is the key of the object for messages
+    ///
+    fn check(
+        &self,
+        path: &str,
+        map: &mut Map<String, JsonValue>,
+        reject_unknown: bool,
+        issues: &mut Vec<String>,
+    ) {
+        let key_path = |key: &str| {
+            if path.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", path, key)
+            }
+        };
+
+        if reject_unknown {
+            for key in self.unknown_keys(&Some(JsonValue::Object(map.clone()))) {
+                issues.push(format!("'{}' is unknown", key_path(&key)));
+            }
+        }
+
+        for (name, prop) in self.iter_sorted() {
+            match map.get_mut(name) {
+                Some(value) => prop.check(&key_path(name), value, reject_unknown, issues),
+                None => {
+                    if prop.required {
+                        issues.push(format!("'{}' is required", key_path(name)));
+                    } else if let (Some(properties), Some(default), false) = (
+                        &prop.properties,
+                        prop.default.as_object(),
+                        prop.documentation_only,
+                    ) {
+                        //
+                        // An absent optional object is checked only when its default is filled
+                        let mut nested = default.clone();
+                        properties.check(&key_path(name), &mut nested, reject_unknown, issues);
+                        map.insert(name.clone(), JsonValue::Object(nested));
+                    } else if !prop.documentation_only && !prop.default.is_null() {
+                        map.insert(name.clone(), prop.default.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+///
+/// Json value of a number, integers stay json integers (9600 and not 9600.0)
+///
+fn number_value(value: f64) -> JsonValue {
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        JsonValue::from(value as i64)
+    } else {
+        serde_json::json!(value)
+    }
 }
 
 impl From<Map<String, JsonValue>> for Props {
@@ -171,3 +687,126 @@ impl From<Map<String, JsonValue>> for Props {
         Self { entries: res }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn test_props() -> Props {
+        let mut props = Props::default();
+        props.add_prop(
+            "port",
+            Prop::new("serial port", PropType::String, JsonValue::Null)
+                .with_default(json!("/dev/ttyUSB0")),
+        );
+        props.add_prop(
+            "baudrate",
+            Prop::new("baudrate", PropType::Number, JsonValue::Null).with_default(json!(9600)),
+        );
+        props.add_bool_prop("debug", "debug mode", false);
+        props
+    }
+
+    #[test]
+    fn test_validate_fills_defaults() {
+        let settings = test_props()
+            .validate(Some(json!({"baudrate": 115200})), true)
+            .unwrap();
+        assert_eq!(
+            settings,
+            json!({"port": "/dev/ttyUSB0", "baudrate": 115200, "debug": false})
+        );
+    }
+
+    #[test]
+    fn test_validate_without_settings() {
+        let settings = test_props().validate(None, true).unwrap();
+        assert_eq!(settings["baudrate"].as_u64(), Some(9600));
+        assert_eq!(settings["debug"], json!(false));
+    }
+
+    #[test]
+    fn test_validate_leaves_documentation_defaults_absent() {
+        let mut props = Props::default();
+        props.add_prop(
+            "usb_vid",
+            Prop::new("vendor id", PropType::Number, json!(0x16c0)).documentation_only(),
+        );
+        props.add_string_prop("usb_serial", "serial number", "");
+        assert_eq!(props.get("usb_vid").unwrap().default, json!(0x16c0));
+
+        let settings = props.validate(None, true).unwrap();
+        assert_eq!(settings, json!({"usb_serial": ""}));
+
+        let schema = props.json_schema();
+        assert_eq!(schema["properties"]["usb_vid"]["examples"], json!([0x16c0]));
+        assert!(schema["properties"]["usb_vid"].get("default").is_none());
+        assert!(!serde_json::to_string(&props)
+            .unwrap()
+            .contains("documentation_only"));
+    }
+
+    #[test]
+    fn test_validate_reports_every_key() {
+        let result =
+            test_props().validate(Some(json!({"port": 3, "debug": "yes", "foo": 1})), true);
+        match result {
+            Err(crate::Error::BadSettings(message)) => {
+                assert!(message.contains("'port'"));
+                assert!(message.contains("'debug'"));
+                assert!(message.contains("'foo' is unknown"));
+            }
+            _ => panic!("expected BadSettings error"),
+        }
+    }
+
+    #[test]
+    fn test_validate_constraints() {
+        let mut nested = Props::default();
+        nested.add_prop(
+            "password",
+            Prop::new("password", PropType::String, JsonValue::Null)
+                .required()
+                .secret(),
+        );
+        nested.add_prop(
+            "timeout",
+            Prop::new("timeout", PropType::Number, JsonValue::Null).with_default(json!(2)),
+        );
+
+        let mut props = Props::default();
+        props.add_ranged_number_prop("voltage", "output voltage", 5, 0.0, 30.0, "V");
+        props.add_enum_prop("mode", "mode", "cv", vec!["cv", "cc"]);
+        props.add_object_prop("auth", "authentication", nested);
+
+        let result = props.validate(Some(json!({"voltage": 42, "mode": "xx", "auth": {}})), true);
+        match result {
+            Err(crate::Error::BadSettings(message)) => {
+                assert!(message.contains("'voltage' must be in [0, 30]"));
+                assert!(message.contains("'mode' must be one of"));
+                assert!(message.contains("'auth.password' is required"));
+            }
+            _ => panic!("expected BadSettings error"),
+        }
+
+        let settings = props
+            .validate(Some(json!({"auth": {"password": "x"}})), true)
+            .unwrap();
+        assert_eq!(settings["auth"]["timeout"], json!(2));
+        assert_eq!(settings["mode"], json!("cv"));
+
+        //
+        // The optional object may be absent even with a required entry
+        let settings = props.validate(None, true).unwrap();
+        assert!(settings.get("auth").is_none());
+    }
+
+    #[test]
+    fn test_validate_keeps_unknown_keys_when_allowed() {
+        let settings = test_props()
+            .validate(Some(json!({"foo": 1})), false)
+            .unwrap();
+        assert_eq!(settings["foo"], json!(1));
+    }
+}
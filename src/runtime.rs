@@ -1,14 +1,25 @@
+pub mod control;
 pub mod notification;
+pub mod structure;
 use crate::engine::EngineBuilder;
+use crate::instance::server::json::JsonAttributeServer;
 
-use crate::{log_debug, log_error, log_trace, Engine, Error, NotificationGroup, ProductionOrder};
-use crate::{Factory, Logger};
+use crate::{
+    log_debug, log_error, log_trace, log_warn, Engine, Error, NotificationGroup, ProductionOrder,
+};
+use crate::{AlertNotification, AttributeMode, Factory, Instance, Logger};
+use control::{ControlCommand, ControlRequest, ControlResponse};
+use notification::publisher::NotificationPublisher;
 use notification::Notification;
+use panduza::task_monitor::TaskHandle;
 use panduza::TaskMonitor;
+use std::collections::{HashMap, HashSet};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
+use std::time::Duration;
+use structure::StructureRegistry;
 use tokio::sync::mpsc::channel;
 use tokio::sync::mpsc::Receiver;
 use tokio::sync::mpsc::Sender;
@@ -21,6 +32,72 @@ static PROD_ORDER_CHANNEL_SIZE: usize = 128;
 ///
 static NOTIFICATION_CHANNEL_SIZE: usize = 1024;
 
+///
+///
+static CONTROL_CHANNEL_SIZE: usize = 32;
+
+/// Max time given to the instances to stop during a shutdown
+///
+static SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where an instance is in its life inside the runtime
+///
+enum InstanceStatus {
+    /// The FSM runs
+    ///
+    Running,
+
+    /// Stop requested, the instance is removed once its FSM has ended
+    ///
+    Stopping,
+
+    /// Stop requested, the FSM runs again once it has ended
+    ///
+    Restarting,
+
+    /// Stop requested, this new instance replaces the old one once its FSM has ended
+    ///
+    Reproducing(Instance),
+
+    /// The FSM has ended, the instance can be restarted, reproduced or stopped
+    ///
+    Stopped,
+}
+
+/// Instance managed by the runtime
+///
+struct RuntimeInstance {
+    /// Handle on the instance
+    ///
+    instance: Instance,
+
+    /// Order used to produce the instance, kept to be able to produce it again
+    ///
+    production_order: ProductionOrder,
+
+    /// Pending lifecycle operation
+    ///
+    status: InstanceStatus,
+
+    /// Id of the FSM task currently running for this instance
+    ///
+    fsm_id: u64,
+}
+
+impl RuntimeInstance {
+    /// Reject the request if an other lifecycle operation is in progress
+    ///
+    fn check_idle(&self, name: &str) -> Result<(), Error> {
+        match self.status {
+            InstanceStatus::Running | InstanceStatus::Stopped => Ok(()),
+            _ => Err(Error::InvalidArgument(format!(
+                "Instance '{}' is already stopping or restarting",
+                name
+            ))),
+        }
+    }
+}
+
 /// Manage the execution instances
 ///
 pub struct Runtime {
@@ -46,6 +123,10 @@ pub struct Runtime {
     /// Sender, allow a sub function to request a register a production order
     production_order_receiver: Option<Receiver<ProductionOrder>>,
 
+    /// Control commands (list, stop, restart...)
+    ///
+    control_channel: (Sender<ControlCommand>, Option<Receiver<ControlCommand>>),
+
     /// Notifications that comes from devices
     /// They will help the underscore device to give informations to the user
     ///
@@ -58,6 +139,38 @@ pub struct Runtime {
     ///
     ///
     task_monitor: TaskMonitor,
+
+    /// Instances produced by this runtime, by name
+    ///
+    instances: HashMap<String, RuntimeInstance>,
+
+    /// FSM tasks report their end here with the name of the instance and the id of the task
+    ///
+    fsm_ended: (Sender<(String, u64)>, Receiver<(String, u64)>),
+
+    /// Id of the last FSM task started
+    ///
+    last_fsm_id: u64,
+
+    /// Names of the orders whose production failure alert is raised
+    ///
+    production_failures: HashSet<String>,
+
+    /// Registry of the instances, classes and attributes exposed by the runtime
+    ///
+    structure: Arc<std::sync::Mutex<StructureRegistry>>,
+
+    /// True to publish the structure on '<root>/_/structure'
+    ///
+    publish_structure: bool,
+
+    /// Server of '<root>/_/structure', created at the start of the task
+    ///
+    structure_server: Option<JsonAttributeServer>,
+
+    /// Also publish the notifications on zenoh if set
+    ///
+    notification_publisher: Option<NotificationPublisher>,
 }
 
 impl Runtime {
@@ -69,6 +182,8 @@ impl Runtime {
         po_receiver: Receiver<ProductionOrder>,
         notifications: Arc<std::sync::Mutex<NotificationGroup>>,
         notification_channel: (Sender<Notification>, Receiver<Notification>),
+        control_channel: (Sender<ControlCommand>, Receiver<ControlCommand>),
+        structure: Arc<std::sync::Mutex<StructureRegistry>>,
     ) -> Self {
         //
         //
@@ -143,9 +258,18 @@ impl Runtime {
             keep_alive: Arc::new(AtomicBool::new(true)),
             // must_stop: Arc::new(AtomicBool::new(false)),
             production_order_receiver: Some(po_receiver),
+            control_channel: (control_channel.0, Some(control_channel.1)),
             notifications: notifications,
             notification_channel: notification_channel,
             task_monitor: task_monitor,
+            instances: HashMap::new(),
+            fsm_ended: channel(CONTROL_CHANNEL_SIZE),
+            last_fsm_id: 0,
+            production_failures: HashSet::new(),
+            structure: structure,
+            publish_structure: false,
+            structure_server: None,
+            notification_publisher: None,
         }
     }
 
@@ -161,6 +285,12 @@ impl Runtime {
         self.notification_channel.0.clone()
     }
 
+    ///
+    ///
+    pub fn control_sender(&self) -> Sender<ControlCommand> {
+        self.control_channel.0.clone()
+    }
+
     ///
     ///
     ///
@@ -172,6 +302,496 @@ impl Runtime {
         &self.engine
     }
 
+    ///
+    ///
+    ///
+    pub fn clone_structure(&self) -> Arc<std::sync::Mutex<StructureRegistry>> {
+        self.structure.clone()
+    }
+
+    /// Also publish the structure snapshot as json on '<root>/_/structure'
+    ///
+    pub fn set_structure_publication(&mut self, enable: bool) {
+        self.publish_structure = enable;
+    }
+
+    /// Also publish each notification on zenoh (see NotificationPublisher for the topics)
+    ///
+    pub fn set_notification_publication(&mut self, enable: bool) {
+        self.notification_publisher = match enable {
+            true => Some(NotificationPublisher::new(
+                self.engine.session.clone(),
+                self.engine.root_topic(self.engine.namespace.clone()),
+            )),
+            false => None,
+        };
+    }
+
+    /// Names of the instances managed by the runtime
+    ///
+    pub fn instance_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.instances.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Produce a new instance and start its FSM
+    ///
+    /// Fail if an instance with the same name already exists
+    ///
+    pub async fn produce(&mut self, production_order: ProductionOrder) -> Result<(), Error> {
+        let name = production_order.name.clone();
+        if self.instances.contains_key(&name) {
+            return Err(Error::InvalidArgument(format!(
+                "An instance named '{}' already exists",
+                name
+            )));
+        }
+
+        let instance = self.factory.produce(
+            self.engine.clone(),
+            production_order.clone(),
+            self.notification_channel.0.clone(),
+            self.engine.namespace.clone(),
+        )?;
+        self.register_structure(&instance, &production_order).await;
+        let fsm_id = self.start_fsm(&name, instance.clone()).await;
+        self.clear_production_failure(&name).await;
+        self.instances.insert(
+            name,
+            RuntimeInstance {
+                instance: instance,
+                production_order: production_order,
+                status: InstanceStatus::Running,
+                fsm_id: fsm_id,
+            },
+        );
+        Ok(())
+    }
+
+    /// Request the named instance to stop
+    ///
+    /// The instance stays in the runtime until it has released its resources,
+    /// so no other instance can be produced with its name meanwhile.
+    ///
+    pub async fn stop_instance(&mut self, name: &str) -> Result<(), Error> {
+        log_debug!(self.logger, "Stop instance '{}'", name);
+        //
+        // Stopping an order that failed to produce only clears its alert
+        if !self.instances.contains_key(name) && self.production_failures.contains(name) {
+            self.clear_production_failure(name).await;
+            return Ok(());
+        }
+        let entry = self.entry_mut(name)?;
+        entry.check_idle(name)?;
+        if matches!(entry.status, InstanceStatus::Stopped) {
+            self.remove_instance(name).await;
+            return Ok(());
+        }
+        entry.status = InstanceStatus::Stopping;
+        Self::request_instance_stop(&entry.instance);
+        Ok(())
+    }
+
+    /// Stop the named instance then run its FSM again
+    ///
+    pub async fn restart_instance(&mut self, name: &str) -> Result<(), Error> {
+        log_debug!(self.logger, "Restart instance '{}'", name);
+        let entry = self.entry_mut(name)?;
+        entry.check_idle(name)?;
+        if matches!(entry.status, InstanceStatus::Stopped) {
+            let instance = entry.instance.clone();
+            instance.clear_stop_request();
+            let fsm_id = self.start_fsm(name, instance).await;
+            let entry = self.entry_mut(name)?;
+            entry.status = InstanceStatus::Running;
+            entry.fsm_id = fsm_id;
+            return Ok(());
+        }
+        entry.status = InstanceStatus::Restarting;
+        Self::request_instance_stop(&entry.instance);
+        Ok(())
+    }
+
+    /// Stop the named instance then produce it again from its production order
+    ///
+    /// The new instance is produced first, the old one is kept if it fails.
+    ///
+    pub async fn reproduce_instance(&mut self, name: &str) -> Result<(), Error> {
+        log_debug!(self.logger, "Reproduce instance '{}'", name);
+        let entry = self.entry_mut(name)?;
+        entry.check_idle(name)?;
+        let production_order = entry.production_order.clone();
+
+        let new_instance = self.factory.produce(
+            self.engine.clone(),
+            production_order,
+            self.notification_channel.0.clone(),
+            self.engine.namespace.clone(),
+        )?;
+
+        //
+        // The new instance starts only once the old one released its resources
+        let entry = self.entry_mut(name)?;
+        if matches!(entry.status, InstanceStatus::Stopped) {
+            self.replace_instance(name, new_instance).await;
+            return Ok(());
+        }
+        entry.status = InstanceStatus::Reproducing(new_instance);
+        Self::request_instance_stop(&entry.instance);
+        Ok(())
+    }
+
+    /// Request the instance to stop without waiting for its state notification
+    ///
+    /// The runtime task drains the notification channel itself, it must never
+    /// wait for room in it.
+    ///
+    fn request_instance_stop(instance: &Instance) {
+        let mut instance = instance.clone();
+        tokio::spawn(async move { instance.request_stop().await });
+    }
+
+    /// Entry of the named instance
+    ///
+    fn entry_mut(&mut self, name: &str) -> Result<&mut RuntimeInstance, Error> {
+        self.instances
+            .get_mut(name)
+            .ok_or(Error::InvalidArgument(format!(
+                "No instance named '{}'",
+                name
+            )))
+    }
+
+    /// Run the FSM of the instance in a monitored task and return the id of the task
+    ///
+    async fn start_fsm(&mut self, name: &str, mut instance: Instance) -> u64 {
+        self.last_fsm_id += 1;
+        let fsm_id = self.last_fsm_id;
+        let fsm_ended = self.fsm_ended.0.clone();
+        let instance_name = name.to_string();
+
+        //
+        // The FSM task ends when the instance is stopped
+        let task_handle = tokio::spawn(async move {
+            instance.run_fsm().await;
+            fsm_ended
+                .send((instance_name, fsm_id))
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        });
+        self.monitor_fsm(name, task_handle).await;
+        fsm_id
+    }
+
+    /// Complete the lifecycle operation of an instance once its FSM has ended
+    ///
+    async fn process_fsm_end(&mut self, name: &str, fsm_id: u64) {
+        let Some(entry) = self.instances.get_mut(name) else {
+            return;
+        };
+        if entry.fsm_id != fsm_id {
+            return;
+        }
+        match std::mem::replace(&mut entry.status, InstanceStatus::Stopped) {
+            InstanceStatus::Stopping => self.remove_instance(name).await,
+            InstanceStatus::Restarting => {
+                let instance = entry.instance.clone();
+                instance.clear_stop_request();
+                let fsm_id = self.start_fsm(name, instance).await;
+                if let Some(entry) = self.instances.get_mut(name) {
+                    entry.status = InstanceStatus::Running;
+                    entry.fsm_id = fsm_id;
+                }
+            }
+            InstanceStatus::Reproducing(new_instance) => {
+                self.replace_instance(name, new_instance).await;
+            }
+            InstanceStatus::Running | InstanceStatus::Stopped => {
+                log_debug!(self.logger, "Instance '{}' stopped", name);
+            }
+        }
+    }
+
+    /// Remove a stopped instance from the runtime
+    ///
+    async fn remove_instance(&mut self, name: &str) {
+        self.instances.remove(name);
+        self.unregister_structure(name).await;
+        self.clear_production_failure(name).await;
+    }
+
+    /// Replace a stopped instance by a new one and start it
+    ///
+    async fn replace_instance(&mut self, name: &str, new_instance: Instance) {
+        let Some(production_order) = self
+            .instances
+            .get(name)
+            .map(|entry| entry.production_order.clone())
+        else {
+            return;
+        };
+        self.unregister_structure(name).await;
+        self.register_structure(&new_instance, &production_order)
+            .await;
+        let fsm_id = self.start_fsm(name, new_instance.clone()).await;
+        if let Some(entry) = self.instances.get_mut(name) {
+            entry.instance = new_instance;
+            entry.status = InstanceStatus::Running;
+            entry.fsm_id = fsm_id;
+        }
+    }
+
+    /// Stop all the instances then end the runtime task
+    ///
+    /// Notifications are still collected while the instances stop, so the last
+    /// states reach the platform.
+    ///
+    pub async fn shutdown(&mut self) -> Result<(), Error> {
+        log_debug!(self.logger, "Shutdown requested");
+        let instances: Vec<Instance> = self
+            .instances
+            .drain()
+            .map(|(_, entry)| entry.instance)
+            .collect();
+        for instance in instances.iter() {
+            Self::request_instance_stop(instance);
+        }
+
+        //
+        // Wait for the stop paths
+        let stopped = futures::future::join_all(instances.iter().map(|i| i.wait_stopped()));
+        tokio::pin!(stopped);
+        let timeout = tokio::time::sleep(SHUTDOWN_TIMEOUT);
+        tokio::pin!(timeout);
+        loop {
+            tokio::select! {
+                _ = &mut stopped => break,
+                _ = &mut timeout => {
+                    log_warn!(self.logger, "Instances still running after {:?}", SHUTDOWN_TIMEOUT);
+                    break;
+                },
+                notif = self.notification_channel.1.recv() => {
+                    if let Some(notif) = notif {
+                        self.process_notification(notif).await;
+                    }
+                },
+            }
+        }
+
+        //
+        // Nothing left to expose
+        self.structure.lock().unwrap().clear();
+        self.publish_structure_snapshot().await;
+
+        //
+        // Release the connection, the library of the plugin can be unloaded after
+        if let Err(e) = self.engine.session.close().await {
+            log_warn!(self.logger, "Session not closed {:?}", e);
+        }
+
+        //
+        // The main loop ends after this request
+        self.keep_alive.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Process a request coming from the control channel
+    ///
+    async fn process_control_request(&mut self, request: ControlRequest) -> ControlResponse {
+        log_debug!(self.logger, "Control request [{:?}]", request);
+        match request {
+            ControlRequest::List => ControlResponse::Instances(self.instance_names()),
+            ControlRequest::Stop(name) => self.stop_instance(&name).await.into(),
+            ControlRequest::Restart(name) => self.restart_instance(&name).await.into(),
+            ControlRequest::Reproduce(name) => self.reproduce_instance(&name).await.into(),
+            ControlRequest::Shutdown => self.shutdown().await.into(),
+            ControlRequest::Acknowledge(id) => self.acknowledge_alert(&id).await.into(),
+        }
+    }
+
+    /// Acknowledge an active alarm
+    ///
+    /// The alarm stays in the table until the attribute clears it.
+    ///
+    pub async fn acknowledge_alert(&mut self, id: &str) -> Result<(), Error> {
+        let alarm = self
+            .structure
+            .lock()
+            .unwrap()
+            .alarm(id)
+            .map(|alarm| alarm.acknowledged())
+            .ok_or(Error::InvalidArgument(format!("No active alarm '{}'", id)))?;
+        self.process_notification(alarm.into()).await;
+        Ok(())
+    }
+
+    /// Listen the acknowledgements of the clients on '<root>/_/alerts/ack'
+    ///
+    /// The payload is the id of the alert. Each one is turned into a control request.
+    ///
+    async fn start_alert_ack_subscriber(&self) {
+        let topic = format!(
+            "{}/_/alerts/ack",
+            self.engine.root_topic(self.engine.namespace.clone())
+        );
+        let session = self.engine.session.clone();
+        let control_sender = self.control_channel.0.clone();
+        let logger = self.logger.clone();
+        let handle = tokio::spawn(async move {
+            let subscriber = session
+                .declare_subscriber(&topic)
+                .await
+                .map_err(|e| e.to_string())?;
+            while let Ok(sample) = subscriber.recv_async().await {
+                let id = match sample.payload().try_to_string() {
+                    Ok(id) => id.trim().to_string(),
+                    Err(e) => {
+                        log_warn!(logger, "Invalid alert acknowledgement {:?}", e);
+                        continue;
+                    }
+                };
+                let (command, response) = ControlCommand::new(ControlRequest::Acknowledge(id));
+                control_sender
+                    .send(command)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                if let Ok(ControlResponse::Error(e)) = response.await {
+                    log_warn!(logger, "Alert acknowledgement rejected ({})", e);
+                }
+            }
+            Ok(())
+        });
+        self.task_monitor
+            .handle_sender()
+            .send(("RT/ALERTS/ACK".to_string(), handle))
+            .await
+            .unwrap();
+    }
+
+    /// Give the FSM task of an instance to the runtime task monitor
+    ///
+    async fn monitor_fsm(&self, name: &str, task_handle: TaskHandle) {
+        self.task_monitor
+            .handle_sender()
+            .send((format!("RT/FSM/{}", name), task_handle))
+            .await
+            .unwrap();
+    }
+
+    /// Topic of the named instance
+    ///
+    fn instance_topic(&self, name: &str) -> String {
+        format!(
+            "{}/{}",
+            self.engine.root_topic(self.engine.namespace.clone()),
+            name
+        )
+    }
+
+    /// Store the notification for the host and keep the structure up to date
+    ///
+    async fn process_notification(&mut self, notification: Notification) {
+        let changed = self.structure.lock().unwrap().update(&notification);
+        if let Some(publisher) = &self.notification_publisher {
+            if let Err(e) = publisher.publish(&notification).await {
+                log_error!(self.logger, "Failed to publish notification {:?}", e);
+            }
+        }
+        self.notifications.lock().unwrap().push(notification);
+        if changed {
+            self.publish_structure_snapshot().await;
+        }
+    }
+
+    /// Add a new instance to the structure
+    ///
+    async fn register_structure(
+        &mut self,
+        instance: &Instance,
+        production_order: &ProductionOrder,
+    ) {
+        let topic = self.instance_topic(&instance.name());
+        let settings = instance.settings().await;
+        self.structure
+            .lock()
+            .unwrap()
+            .add_instance(topic, production_order.dref(), settings);
+        self.publish_structure_snapshot().await;
+    }
+
+    /// Remove an instance from the structure
+    ///
+    async fn unregister_structure(&mut self, name: &str) {
+        let topic = self.instance_topic(name);
+        self.structure.lock().unwrap().remove_instance(&topic);
+        self.publish_structure_snapshot().await;
+    }
+
+    /// Start the server of '<root>/_/structure' if the publication is enabled
+    ///
+    async fn start_structure_server(&mut self) {
+        if !self.publish_structure {
+            return;
+        }
+        let topic = format!(
+            "{}/_/structure",
+            self.engine.root_topic(self.engine.namespace.clone())
+        );
+        let cmd_receiver = self
+            .engine
+            .register_listener(format!("{}/cmd", topic), 50)
+            .await;
+        self.structure_server = Some(
+            JsonAttributeServer::new(
+                self.engine.session.clone(),
+                topic,
+                AttributeMode::ReadOnly,
+                cmd_receiver,
+                self.task_monitor.handle_sender(),
+                self.notification_channel.0.clone(),
+            )
+            .await,
+        );
+        self.publish_structure_snapshot().await;
+    }
+
+    /// Publish the structure if the publication is enabled
+    ///
+    async fn publish_structure_snapshot(&self) {
+        if let Some(server) = &self.structure_server {
+            let snapshot = self.structure.lock().unwrap().snapshot();
+            if let Err(e) = server.set(snapshot).await {
+                log_error!(self.logger, "Failed to publish the structure {:?}", e);
+            }
+        }
+    }
+
+    /// Report a production failure to the user through an alert
+    ///
+    async fn alert_production_failure(&mut self, name: &str, error: Error) {
+        log_error!(self.logger, "Production of '{}' failed: {:?}", name, error);
+        let topic = self.instance_topic(name);
+        self.production_failures.insert(name.to_string());
+        self.process_notification(AlertNotification::new(topic, format!("{:?}", error)).into())
+            .await;
+    }
+
+    /// Clear the production failure alert of the order, if raised
+    ///
+    /// Processed directly like the alert itself, the runtime task drains the
+    /// notification channel and must not wait for room in it.
+    ///
+    async fn clear_production_failure(&mut self, name: &str) {
+        if !self.production_failures.remove(name) {
+            return;
+        }
+        let topic = self.instance_topic(name);
+        self.process_notification(AlertNotification::cleared(topic.clone(), topic).into())
+            .await;
+    }
+
     ///
     /// Main task of the runtime, it consume the object itself
     ///
@@ -189,6 +809,21 @@ impl Runtime {
                     "Object 'production_order_receiver' is 'None'".to_string(),
                 ))?;
 
+        //
+        // Remove control receiver from self
+        let mut control_receiver =
+            self.control_channel
+                .1
+                .take()
+                .ok_or(crate::Error::InternalLogic(
+                    "Object 'control_receiver' is 'None'".to_string(),
+                ))?;
+
+        //
+        // Expose the structure to the clients
+        self.start_structure_server().await;
+        self.start_alert_ack_subscriber().await;
+
         //
         while self.keep_alive.load(Ordering::Relaxed) {
             tokio::select! {
@@ -199,37 +834,28 @@ impl Runtime {
 
                     log_debug!(self.logger, "!!! PROD REQUEST ! [{:?}]", production_order );
 
-                    let name = production_order.as_ref().unwrap().name.clone();
-
-                    // let mut production_order = ProductionOrder::new("panduza.picoha-dio", "testdevice");
-                    // production_order.device_settings = json!({});
-                    let mut instance =
-                        self.factory
-                            .produce(
-                                self.engine.clone(),
-                                production_order.unwrap(),
-                                self.notification_channel.0.clone(),
-                                self.engine.namespace.clone(),
-                            );
-                    //
-                    let task_handle = tokio::spawn(async move {
-                        loop {
-                            instance.run_fsm().await;
+                    let production_order = production_order.unwrap();
+                    let name = production_order.name.clone();
+                    if let Err(e) = self.produce(production_order).await {
+                        self.alert_production_failure(&name, e).await;
+                    }
+                },
+                command = control_receiver.recv() => {
+                    if let Some(command) = command {
+                        let response = self.process_control_request(command.request).await;
+                        if command.reply.send(response).is_err() {
+                            log_debug!(self.logger, "Control response dropped by the requester");
                         }
-                    });
-
-                    //
-                    self.task_monitor
-                        .handle_sender()
-                        .send((format!("RT/FSM/{}", name), task_handle))
-                        .await
-                        .unwrap();
-
-
+                    }
                 },
                 notif = self.notification_channel.1.recv() => {
                     log_trace!(self.logger,  "NOTIF [{:?}]", notif );
-                    self.notifications.lock().unwrap().push(notif.unwrap()); //serait la source du problème
+                    self.process_notification(notif.unwrap()).await; //serait la source du problème
+                },
+                ended = self.fsm_ended.1.recv() => {
+                    if let Some((name, fsm_id)) = ended {
+                        self.process_fsm_end(&name, fsm_id).await;
+                    }
                 },
 
             }
@@ -259,6 +885,22 @@ pub struct RuntimeBuilder {
     ///
     ///
     pub notification_channel: (Sender<Notification>, Receiver<Notification>),
+
+    ///
+    ///
+    pub control_channel: (Sender<ControlCommand>, Receiver<ControlCommand>),
+
+    ///
+    ///
+    pub structure: Arc<std::sync::Mutex<StructureRegistry>>,
+
+    /// See `Runtime::set_structure_publication`
+    ///
+    publish_structure: bool,
+
+    /// See `Runtime::set_notification_publication`
+    ///
+    publish_notifications: bool,
 }
 
 impl RuntimeBuilder {
@@ -267,6 +909,8 @@ impl RuntimeBuilder {
 
         let (not_tx, not_rx) = channel::<Notification>(NOTIFICATION_CHANNEL_SIZE);
 
+        let (ctl_tx, ctl_rx) = channel::<ControlCommand>(CONTROL_CHANNEL_SIZE);
+
         (
             Self {
                 factory: factory,
@@ -274,6 +918,10 @@ impl RuntimeBuilder {
                 po_receiver: po_rx,
                 notifications: Arc::new(std::sync::Mutex::new(NotificationGroup::new())),
                 notification_channel: (not_tx, not_rx),
+                control_channel: (ctl_tx, ctl_rx),
+                structure: Arc::new(std::sync::Mutex::new(StructureRegistry::default())),
+                publish_structure: false,
+                publish_notifications: false,
             },
             po_tx,
         )
@@ -291,15 +939,53 @@ impl RuntimeBuilder {
         self.notification_channel.0.clone()
     }
 
+    ///
+    ///
+    pub fn control_sender(&self) -> Sender<ControlCommand> {
+        self.control_channel.0.clone()
+    }
+
+    /// Max number of notifications kept until the host pulls them
+    ///
+    /// Above it, notifications are dropped and an overflow notification tells the
+    /// host to resync.
+    ///
+    pub fn set_notification_capacity(&mut self, capacity: usize) {
+        self.notifications.lock().unwrap().set_capacity(capacity);
+    }
+
+    ///
+    ///
+    pub fn clone_structure(&self) -> Arc<std::sync::Mutex<StructureRegistry>> {
+        self.structure.clone()
+    }
+
+    /// See `Runtime::set_structure_publication`
+    ///
+    pub fn set_structure_publication(&mut self, enable: bool) {
+        self.publish_structure = enable;
+    }
+
+    /// See `Runtime::set_notification_publication`
+    ///
+    pub fn set_notification_publication(&mut self, enable: bool) {
+        self.publish_notifications = enable;
+    }
+
     pub async fn start(self) -> Runtime {
         let rr = self.engine_builder.build().await;
 
-        Runtime::new(
+        let mut runtime = Runtime::new(
             self.factory,
             rr,
             self.po_receiver,
             self.notifications,
             self.notification_channel,
-        )
+            self.control_channel,
+            self.structure,
+        );
+        runtime.set_structure_publication(self.publish_structure);
+        runtime.set_notification_publication(self.publish_notifications);
+        runtime
     }
 }
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::{c_char, CStr, CString};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", content = "name", rename_all = "snake_case")]
+/// Requests that can be sent to a running runtime
+///
+/// ## Json Structure
+///
+/// { "command": "restart", "name": "my_instance" }
+///
+pub enum ControlRequest {
+    /// List the names of the instances managed by the runtime
+    ///
+    List,
+
+    /// Stop the instance and remove it from the runtime
+    ///
+    Stop(String),
+
+    /// Stop the instance then run its FSM again with the same actions
+    ///
+    Restart(String),
+
+    /// Stop the instance then produce it again from its production order
+    ///
+    Reproduce(String),
+
+    /// Stop all the instances and end the runtime
+    ///
+    Shutdown,
+
+    /// Acknowledge the active alarm with this id
+    ///
+    Acknowledge(String),
+}
+
+impl ControlRequest {
+    /// Converts a C-style string pointer into a `ControlRequest`
+    ///
+    pub fn from_c_str_ptr(c_str: *const c_char) -> Result<Self, crate::Error> {
+        //
+        //
+        if c_str.is_null() {
+            return Err(crate::Error::InvalidArgument(
+                "Null C string pointer".to_string(),
+            ));
+        }
+
+        //
+        //
+        let c_str = unsafe { CStr::from_ptr(c_str) };
+        let str = c_str
+            .to_str()
+            .map_err(|e| crate::Error::InvalidArgument(format!("Invalid C string: {:?}", e)))?;
+
+        serde_json::from_str(str).map_err(|e| {
+            crate::Error::InvalidArgument(format!("Failed to deserialize JSON: {:?}", e))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", content = "data", rename_all = "snake_case")]
+/// Response of the runtime to a `ControlRequest`
+///
+pub enum ControlResponse {
+    /// Request processed
+    ///
+    Ok,
+
+    /// Names of the instances managed by the runtime
+    ///
+    Instances(Vec<String>),
+
+    /// Request rejected
+    ///
+    Error(String),
+}
+
+impl ControlResponse {
+    /// Converts the response into a C string
+    ///
+    pub fn to_c_string(&self) -> Result<CString, crate::Error> {
+        let json_str =
+            serde_json::to_string(self).expect("Failed to serialize ControlResponse to JSON");
+        CString::new(json_str)
+            .map_err(|e| crate::Error::InternalLogic(format!("Failed to build CString ({:?})", e)))
+    }
+}
+
+impl From<Result<(), crate::Error>> for ControlResponse {
+    fn from(result: Result<(), crate::Error>) -> Self {
+        match result {
+            Ok(_) => ControlResponse::Ok,
+            Err(e) => ControlResponse::Error(format!("{:?}", e)),
+        }
+    }
+}
+
+/// Request sent on the runtime control channel with the way back for the response
+///
+#[derive(Debug)]
+pub struct ControlCommand {
+    /// What the runtime must do
+    ///
+    pub request: ControlRequest,
+
+    /// Where the runtime must send the response
+    ///
+    pub reply: oneshot::Sender<ControlResponse>,
+}
+
+impl ControlCommand {
+    /// Create a new command and the receiver of its response
+    ///
+    pub fn new(request: ControlRequest) -> (Self, oneshot::Receiver<ControlResponse>) {
+        let (reply, response) = oneshot::channel();
+        (
+            Self {
+                request: request,
+                reply: reply,
+            },
+            response,
+        )
+    }
+
+    /// Send a request to the runtime and block the current thread until its response
+    ///
+    /// Made for sync contexts like the plugin C interface. The runtime must run
+    /// on an other thread.
+    ///
+    pub fn blocking_request(
+        sender: &Sender<ControlCommand>,
+        request: ControlRequest,
+    ) -> ControlResponse {
+        let (command, response) = Self::new(request);
+        match sender.try_send(command) {
+            Ok(_) => futures::executor::block_on(response).unwrap_or(ControlResponse::Error(
+                "Runtime dropped the request".to_string(),
+            )),
+            Err(e) => ControlResponse::Error(format!("Runtime unreachable ({:?})", e)),
+        }
+    }
+}
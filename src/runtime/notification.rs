@@ -3,11 +3,14 @@ pub mod attribute;
 pub mod class;
 pub mod enablement;
 pub mod group;
+pub mod overflow;
+pub mod publisher;
 pub mod state;
-pub use alert::AlertNotification;
+pub use alert::{AlertNotification, AlertSeverity, AlertState};
 pub use attribute::AttributeNotification;
 pub use class::ClassNotification;
 pub use enablement::EnablementNotification;
+pub use overflow::OverflowNotification;
 pub use state::StateNotification;
 
 use serde::{Deserialize, Serialize};
@@ -16,7 +19,7 @@ use serde::{Deserialize, Serialize};
 /// Available Runtime Notification Types
 ///
 pub enum Notification {
-    /// There is a warning message coming from the instance
+    /// An alert of the instance has been raised, acknowledged or cleared
     ///
     Alert(AlertNotification),
 
@@ -38,4 +41,8 @@ pub enum Notification {
     /// can erase the attribute or the class. Choose Enable/Disable instead.
     ///
     Enablement(EnablementNotification),
+
+    /// Notifications have been dropped because the host did not pull them fast enough
+    ///
+    Overflow(OverflowNotification),
 }
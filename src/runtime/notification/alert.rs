@@ -1,8 +1,65 @@
 use super::Notification;
 use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+/// Severity of an alert
+///
+pub enum AlertSeverity {
+    /// Information for the operator
+    ///
+    Info,
+
+    /// Something unusual that does not prevent the instance from working
+    ///
+    Warning,
+
+    /// An operation failed
+    ///
+    #[default]
+    Error,
+
+    /// The instance cannot work anymore
+    ///
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// Life cycle of an alert
+///
+pub enum AlertState {
+    /// The condition is active and nobody acknowledged it yet
+    ///
+    Raised,
+
+    /// An operator acknowledged the alert, the condition may still be active
+    ///
+    Acknowledged,
+
+    /// The condition is over
+    ///
+    Cleared,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+/// Alert raised, acknowledged or cleared on a topic
+///
+/// ## Json Structure
+///
+/// {
+///     "id": "pza/dev/ctrl/voltage", "topic": "pza/dev/ctrl/voltage", "message": "Overvoltage",
+///     "severity": "critical", "state": "raised", "timestamp": 1700000000000
+/// }
+///
 pub struct AlertNotification {
+    /// Stable identifier, the same for each raise and clear of the alert
+    ///
+    /// Default to the topic, so one attribute holds one alert at a time
+    ///
+    pub id: String,
+
     /// Class topic
     ///
     pub topic: String,
@@ -10,15 +67,69 @@ pub struct AlertNotification {
     /// Alert message
     ///
     pub message: String,
+
+    /// Severity of the alert
+    ///
+    pub severity: AlertSeverity,
+
+    /// Where the alert is in its life cycle
+    ///
+    pub state: AlertState,
+
+    /// Time of the event in milliseconds since the unix epoch
+    ///
+    pub timestamp: u64,
 }
 
 impl AlertNotification {
-    /// Create object
+    /// Create a raised alert with the default severity
     ///
-    pub fn new(topic: String, message: String) -> Self {
+    pub fn new<A: Into<String>, B: Into<String>>(topic: A, message: B) -> Self {
+        let topic = topic.into();
         Self {
+            id: topic.clone(),
             topic: topic,
-            message: message,
+            message: message.into(),
+            severity: AlertSeverity::default(),
+            state: AlertState::Raised,
+            timestamp: now_millis(),
+        }
+    }
+
+    /// Create the notification that clears the alert 'id' of the topic
+    ///
+    pub fn cleared<A: Into<String>, B: Into<String>>(topic: A, id: B) -> Self {
+        Self {
+            id: id.into(),
+            topic: topic.into(),
+            message: String::new(),
+            severity: AlertSeverity::Info,
+            state: AlertState::Cleared,
+            timestamp: now_millis(),
+        }
+    }
+
+    /// Set the severity
+    ///
+    pub fn with_severity(mut self, severity: AlertSeverity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Set the identifier
+    ///
+    pub fn with_id<A: Into<String>>(mut self, id: A) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    /// Copy of this alert acknowledged now
+    ///
+    pub fn acknowledged(&self) -> Self {
+        Self {
+            state: AlertState::Acknowledged,
+            timestamp: now_millis(),
+            ..self.clone()
         }
     }
 }
@@ -30,3 +141,12 @@ impl Into<Notification> for AlertNotification {
         Notification::Alert(self)
     }
 }
+
+/// Current time in milliseconds since the unix epoch
+///
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -3,7 +3,7 @@ use serde_json::Value as JsonValue;
 
 use super::Notification;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AttributeMode {
     #[serde(rename = "RO")]
     ReadOnly,
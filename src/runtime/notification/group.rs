@@ -1,16 +1,53 @@
+use std::collections::VecDeque;
 use std::ffi::{c_char, CString};
 
-use super::Notification;
+use serde::{Deserialize, Serialize};
 
-pub struct NotificationGroup {
-    elements: Vec<Notification>,
+use super::{Notification, OverflowNotification};
+
+/// Default max number of notifications kept between two pulls
+///
+pub const DEFAULT_NOTIFICATION_CAPACITY: usize = 4096;
 
-    pulled_elements: Vec<Notification>,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Notification with its sequence number
+///
+/// ## Json Structure
+///
+/// { "seq": 12, "State": { "topic": "pza/dev", "state": "Running" } }
+///
+pub struct SequencedNotification {
+    /// Position of the notification in the runtime flow, starts at 0
+    ///
+    /// A gap between two pulled sequences means that notifications were dropped.
+    ///
+    pub seq: u64,
+
+    /// The notification itself
+    ///
+    #[serde(flatten)]
+    pub notification: Notification,
+}
+
+pub struct NotificationGroup {
+    elements: VecDeque<SequencedNotification>,
 
     ///
     /// Keep it here to maintain pointer validity
     ///
     pulled_elements_serialized: CString,
+
+    /// Max number of elements kept between two pulls
+    ///
+    capacity: usize,
+
+    /// Sequence number of the next notification
+    ///
+    next_seq: u64,
+
+    /// Notifications dropped since the last pull
+    ///
+    dropped: u64,
 }
 
 impl NotificationGroup {
@@ -18,27 +55,69 @@ impl NotificationGroup {
     ///
     ///
     pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_NOTIFICATION_CAPACITY)
+    }
+
+    ///
+    /// Create a group that keeps at most 'capacity' notifications between two pulls
+    ///
+    pub fn with_capacity(capacity: usize) -> Self {
         Self {
-            elements: Vec::new(),
-            pulled_elements: Vec::new(),
+            elements: VecDeque::new(),
             pulled_elements_serialized: CString::default(),
+            capacity: capacity,
+            next_seq: 0,
+            dropped: 0,
         }
     }
 
     ///
+    /// Change the capacity, elements above the new capacity are kept until the next pull
+    ///
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+
+    ///
+    /// Max number of notifications kept between two pulls
+    ///
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    ///
+    /// Store the notification, or count it as dropped if the group is full
     ///
+    /// A dropped notification still consumes its sequence number.
     ///
     pub fn push(&mut self, n: Notification) {
-        self.elements.push(n);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        if self.elements.len() >= self.capacity {
+            self.dropped += 1;
+            return;
+        }
+        self.elements.push_back(SequencedNotification {
+            seq: seq,
+            notification: n,
+        });
     }
 
     ///
+    /// Take all the stored notifications
     ///
+    /// If some notifications have been dropped, an overflow notification is added at the end.
     ///
-    pub fn pull(&mut self) -> Vec<Notification> {
-        self.pulled_elements = self.elements.clone();
-        self.elements.clear();
-        self.pulled_elements.clone()
+    pub fn pull(&mut self) -> Vec<SequencedNotification> {
+        if self.dropped > 0 {
+            self.elements.push_back(SequencedNotification {
+                seq: self.next_seq,
+                notification: OverflowNotification::new(self.dropped).into(),
+            });
+            self.next_seq += 1;
+            self.dropped = 0;
+        }
+        self.elements.drain(..).collect()
     }
 
     ///
@@ -46,20 +125,11 @@ impl NotificationGroup {
     ///
     pub fn pull_and_serialize(&mut self) -> *const c_char {
         //
-        // despair logs
-        // println!("DEBUG: pull_and_serialize()");
-
-        //
-        // Clone notification elements to make them static
-        self.pulled_elements = self.elements.clone();
-
-        //
-        // despair logs
-        // println!("DEBUG: {:?}", self.pulled_elements);
+        // Only the serialized string must stay valid for the caller
+        let pulled = self.pull();
 
-        self.elements.clear();
-        let json_str = serde_json::to_string(&self.pulled_elements)
-            .expect("Failed to serialize pulled_elements to JSON");
+        let json_str = serde_json::to_string(&pulled)
+            .expect("Failed to serialize the pulled notifications to JSON");
         let as_c_string = CString::new(json_str);
         match as_c_string {
             Ok(o) => {
@@ -73,3 +143,42 @@ impl NotificationGroup {
         self.pulled_elements_serialized.as_c_str().as_ptr()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AlertNotification;
+
+    #[test]
+    fn test_overflow_marker() {
+        let mut group = NotificationGroup::with_capacity(2);
+        for i in 0..5 {
+            group.push(AlertNotification::new(format!("pza/dev{}", i), "boom".to_string()).into());
+        }
+
+        let pulled = group.pull();
+        let seqs: Vec<u64> = pulled.iter().map(|n| n.seq).collect();
+        assert_eq!(seqs, vec![0, 1, 5]);
+        match &pulled[2].notification {
+            Notification::Overflow(o) => assert_eq!(o.dropped, 3),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        group.push(AlertNotification::new("pza/dev", "boom".to_string()).into());
+        let pulled = group.pull();
+        assert_eq!(pulled.len(), 1);
+        assert_eq!(pulled[0].seq, 6);
+    }
+
+    #[test]
+    fn test_sequenced_json() {
+        let mut group = NotificationGroup::new();
+        group.push(AlertNotification::new("pza/dev", "boom".to_string()).into());
+        let json = serde_json::to_value(group.pull()).unwrap();
+        assert_eq!(json[0]["seq"], 0);
+        assert_eq!(json[0]["Alert"]["topic"], "pza/dev");
+
+        let back: Vec<SequencedNotification> = serde_json::from_value(json).unwrap();
+        assert_eq!(back[0].seq, 0);
+    }
+}
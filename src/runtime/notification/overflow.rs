@@ -0,0 +1,29 @@
+use super::Notification;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Notification for notifications dropped because the group was full
+///
+/// The host missed some events and must resync the full state.
+///
+pub struct OverflowNotification {
+    /// Number of notifications dropped since the last pull
+    ///
+    pub dropped: u64,
+}
+
+impl OverflowNotification {
+    /// Create new object
+    ///
+    pub fn new(dropped: u64) -> Self {
+        Self { dropped: dropped }
+    }
+}
+
+/// Implicit convertion
+///
+impl Into<Notification> for OverflowNotification {
+    fn into(self) -> Notification {
+        Notification::Overflow(self)
+    }
+}
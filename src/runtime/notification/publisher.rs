@@ -0,0 +1,124 @@
+use super::Notification;
+use crate::Error;
+use zenoh::Session;
+
+/// Publish the runtime notifications directly on zenoh
+///
+/// Each notification is published as the json of its inner object on a topic
+/// under the root topic of the engine.
+///
+/// ## Topics
+///
+/// | Notification | Topic                       | Payload                   |
+/// |--------------|-----------------------------|---------------------------|
+/// | Alert        | `<root>/_/alerts`           | `AlertNotification`       |
+/// | State        | `<root>/_/states/<instance>`| `StateNotification`       |
+/// | Class        | `<root>/_/classes`          | `ClassNotification`       |
+/// | Attribute    | `<root>/_/attributes`       | `AttributeNotification`   |
+/// | Enablement   | `<root>/_/enablements`      | `EnablementNotification`  |
+/// | Overflow     | `<root>/_/overflows`        | `OverflowNotification`    |
+///
+/// ## Json Payload (State)
+///
+/// { "topic": "pza/my_instance", "state": "Running" }
+///
+/// Clients acknowledge an alert by publishing its id on `<root>/_/alerts/ack`.
+///
+#[derive(Clone)]
+pub struct NotificationPublisher {
+    /// Session used to publish
+    ///
+    session: Session,
+
+    /// Root topic of the engine ("pza" or "<namespace>/pza")
+    ///
+    root_topic: String,
+}
+
+impl NotificationPublisher {
+    /// Create a new publisher
+    ///
+    pub fn new<A: Into<String>>(session: Session, root_topic: A) -> Self {
+        Self {
+            session: session,
+            root_topic: root_topic.into(),
+        }
+    }
+
+    /// Topic where the notification is published
+    ///
+    pub fn topic_for(root_topic: &str, notification: &Notification) -> String {
+        match notification {
+            Notification::Alert(_) => format!("{}/_/alerts", root_topic),
+            Notification::State(n) => {
+                let instance = n
+                    .topic
+                    .strip_prefix(&format!("{}/", root_topic))
+                    .unwrap_or(&n.topic);
+                format!("{}/_/states/{}", root_topic, instance)
+            }
+            Notification::Class(_) => format!("{}/_/classes", root_topic),
+            Notification::Attribute(_) => format!("{}/_/attributes", root_topic),
+            Notification::Enablement(_) => format!("{}/_/enablements", root_topic),
+            Notification::Overflow(_) => format!("{}/_/overflows", root_topic),
+        }
+    }
+
+    /// Json payload of the notification
+    ///
+    pub fn payload_for(notification: &Notification) -> Result<String, Error> {
+        let payload = match notification {
+            Notification::Alert(n) => serde_json::to_string(n),
+            Notification::State(n) => serde_json::to_string(n),
+            Notification::Class(n) => serde_json::to_string(n),
+            Notification::Attribute(n) => serde_json::to_string(n),
+            Notification::Enablement(n) => serde_json::to_string(n),
+            Notification::Overflow(n) => serde_json::to_string(n),
+        };
+        payload.map_err(|e| Error::SerializeFailure(format!("{:?}", e)))
+    }
+
+    /// Publish the notification
+    ///
+    pub async fn publish(&self, notification: &Notification) -> Result<(), Error> {
+        let topic = Self::topic_for(&self.root_topic, notification);
+        let payload = Self::payload_for(notification)?;
+        let pyl_size = payload.len();
+        self.session
+            .put(&topic, payload)
+            .await
+            .map_err(|e| Error::PublishError {
+                topic: topic,
+                pyl_size: pyl_size,
+                cause: e.to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AlertNotification, StateNotification};
+    use panduza::InstanceState;
+
+    #[test]
+    fn test_topics() {
+        let state: Notification =
+            StateNotification::new("ns/pza/dev".to_string(), InstanceState::Running).into();
+        assert_eq!(
+            NotificationPublisher::topic_for("ns/pza", &state),
+            "ns/pza/_/states/dev"
+        );
+
+        let alert: Notification = AlertNotification::new("pza/dev", "boom").into();
+        assert_eq!(
+            NotificationPublisher::topic_for("pza", &alert),
+            "pza/_/alerts"
+        );
+        let payload: serde_json::Value =
+            serde_json::from_str(&NotificationPublisher::payload_for(&alert).unwrap()).unwrap();
+        assert_eq!(payload["topic"], "pza/dev");
+        assert_eq!(payload["message"], "boom");
+        assert_eq!(payload["state"], "raised");
+    }
+}
@@ -11,6 +11,26 @@ pub struct StateNotification {
     /// State of the instance
     ///
     pub state: InstanceState,
+
+    /// Consecutive reboots since the last stable run
+    ///
+    #[serde(default)]
+    pub attempts: u32,
+
+    /// Reboots allowed by the reboot policy, None for no limit
+    ///
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+
+    /// Message of the last error of the instance
+    ///
+    #[serde(default)]
+    pub last_error: Option<String>,
+
+    /// True once the instance stays in Error because its reboot policy has no attempt left
+    ///
+    #[serde(default)]
+    pub given_up: bool,
 }
 
 impl StateNotification {
@@ -20,6 +40,10 @@ impl StateNotification {
         Self {
             topic: name,
             state: state,
+            attempts: 0,
+            max_attempts: None,
+            last_error: None,
+            given_up: false,
         }
     }
 }
@@ -0,0 +1,300 @@
+use super::notification::{AlertNotification, AlertState, AttributeMode, Notification};
+use crate::log_error;
+use crate::InstanceSettings;
+use crate::Logger;
+use panduza::InstanceState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::{c_char, CString};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Instance entry of the structure
+///
+pub struct InstanceEntry {
+    /// Reference of the driver that produced the instance
+    ///
+    pub dref: String,
+
+    /// Settings given in the production order
+    ///
+    pub settings: Option<InstanceSettings>,
+
+    /// Last known state
+    ///
+    pub state: Option<InstanceState>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Class entry of the structure
+///
+pub struct ClassEntry {
+    /// Interfaces tags
+    ///
+    pub tags: Vec<String>,
+
+    /// False when the class has been disabled
+    ///
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Attribute entry of the structure
+///
+pub struct AttributeEntry {
+    /// Type of the attribute
+    ///
+    #[serde(rename = "type")]
+    pub typee: String,
+
+    /// Access mode
+    ///
+    pub mode: AttributeMode,
+
+    /// Information for the user
+    ///
+    pub info: Option<String>,
+
+    /// Settings of the attribute (range, unit...)
+    ///
+    pub settings: Option<serde_json::Value>,
+
+    /// False when the attribute has been disabled
+    ///
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// Authoritative registry of what the runtime exposes, keyed by topic
+///
+/// It is built from the production orders and the notification flow, a host
+/// that attached late or dropped notifications can resync from a snapshot.
+///
+/// ## Json Structure
+///
+/// {
+///     "instances": { "pza/dev": { "dref": "...", "settings": {}, "state": "Running" } },
+///     "classes": { "pza/dev/ctrl": { "tags": [], "enabled": true } },
+///     "attributes": {
+///         "pza/dev/ctrl/voltage": {
+///             "type": "number", "mode": "RW", "info": null, "settings": null, "enabled": true
+///         }
+///     },
+///     "alarms": { "pza/dev/ctrl/voltage": { "id": "...", "state": "raised", ... } }
+/// }
+///
+pub struct StructureRegistry {
+    /// Instances by topic
+    ///
+    pub instances: BTreeMap<String, InstanceEntry>,
+
+    /// Classes by topic
+    ///
+    pub classes: BTreeMap<String, ClassEntry>,
+
+    /// Attributes by topic
+    ///
+    pub attributes: BTreeMap<String, AttributeEntry>,
+
+    /// Active alarms (raised or acknowledged) by alert id
+    ///
+    pub alarms: BTreeMap<String, AlertNotification>,
+
+    ///
+    /// Keep it here to maintain pointer validity
+    ///
+    #[serde(skip)]
+    snapshot_serialized: CString,
+}
+
+impl StructureRegistry {
+    /// Register a produced instance
+    ///
+    pub fn add_instance<A: Into<String>, B: Into<String>>(
+        &mut self,
+        topic: A,
+        dref: B,
+        settings: Option<InstanceSettings>,
+    ) {
+        self.instances.insert(
+            topic.into(),
+            InstanceEntry {
+                dref: dref.into(),
+                settings: settings,
+                state: None,
+            },
+        );
+    }
+
+    /// Remove an instance with its classes and attributes
+    ///
+    pub fn remove_instance(&mut self, topic: &str) {
+        let prefix = format!("{}/", topic);
+        self.instances.remove(topic);
+        self.classes.retain(|t, _| !t.starts_with(&prefix));
+        self.attributes.retain(|t, _| !t.starts_with(&prefix));
+        self.alarms
+            .retain(|_, a| a.topic != topic && !a.topic.starts_with(&prefix));
+    }
+
+    /// Remove everything
+    ///
+    pub fn clear(&mut self) {
+        self.instances.clear();
+        self.classes.clear();
+        self.attributes.clear();
+        self.alarms.clear();
+    }
+
+    /// Active alarm with this id
+    ///
+    pub fn alarm(&self, id: &str) -> Option<&AlertNotification> {
+        self.alarms.get(id)
+    }
+
+    /// Update the registry from a notification
+    ///
+    /// Return true if the structure changed, a notification that repeats the
+    /// current content does not change it
+    ///
+    pub fn update(&mut self, notification: &Notification) -> bool {
+        match notification {
+            Notification::State(n) => match self.instances.get_mut(&n.topic) {
+                Some(instance) => {
+                    let changed = instance.state.as_ref().map(std::mem::discriminant)
+                        != Some(std::mem::discriminant(&n.state));
+                    instance.state = Some(n.state.clone());
+                    changed
+                }
+                None => false,
+            },
+            Notification::Class(n) => {
+                let entry = ClassEntry {
+                    tags: n.tags.clone(),
+                    enabled: true,
+                };
+                self.classes.insert(n.topic.clone(), entry.clone()) != Some(entry)
+            }
+            Notification::Attribute(n) => {
+                let entry = AttributeEntry {
+                    typee: n.typee.clone(),
+                    mode: n.mode().clone(),
+                    info: n.info().clone(),
+                    settings: n.settings().clone(),
+                    enabled: true,
+                };
+                self.attributes.insert(n.topic(), entry.clone()) != Some(entry)
+            }
+            Notification::Enablement(n) => {
+                if let Some(class) = self.classes.get_mut(&n.topic) {
+                    let changed = class.enabled != n.enabled;
+                    class.enabled = n.enabled;
+                    return changed;
+                }
+                if let Some(attribute) = self.attributes.get_mut(&n.topic) {
+                    let changed = attribute.enabled != n.enabled;
+                    attribute.enabled = n.enabled;
+                    return changed;
+                }
+                false
+            }
+            Notification::Alert(n) => match n.state {
+                AlertState::Raised => {
+                    self.alarms.insert(n.id.clone(), n.clone());
+                    true
+                }
+                AlertState::Acknowledged => match self.alarms.get_mut(&n.id) {
+                    Some(alarm) => {
+                        alarm.state = AlertState::Acknowledged;
+                        alarm.timestamp = n.timestamp;
+                        true
+                    }
+                    None => false,
+                },
+                AlertState::Cleared => self.alarms.remove(&n.id).is_some(),
+            },
+            Notification::Overflow(_) => false,
+        }
+    }
+
+    /// Snapshot of the registry as json
+    ///
+    pub fn snapshot(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("Failed to serialize StructureRegistry to JSON")
+    }
+
+    /// Snapshot of the registry for the C interface
+    ///
+    pub fn snapshot_and_serialize(&mut self) -> *const c_char {
+        self.snapshot_serialized = match CString::new(self.snapshot().to_string()) {
+            Ok(o) => o,
+            Err(e) => {
+                log_error!(
+                    Logger::new_for_runtime(),
+                    "Failed to serialize structure snapshot {:?}",
+                    e
+                );
+                CString::default()
+            }
+        };
+        self.snapshot_serialized.as_c_str().as_ptr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AttributeNotification, ClassNotification, StateNotification};
+
+    #[test]
+    fn test_registry_from_notifications() {
+        let mut registry = StructureRegistry::default();
+        registry.add_instance("pza/dev", "vendor.model", None);
+        registry.add_instance("pza/other", "vendor.model", None);
+        registry.update(&ClassNotification::new("pza/dev/ctrl", vec!["psu".to_string()]).into());
+        registry.update(
+            &AttributeNotification::new(
+                "pza/dev/ctrl/voltage",
+                "number",
+                AttributeMode::ReadWrite,
+                None,
+                None,
+            )
+            .into(),
+        );
+        let running: Notification =
+            StateNotification::new("pza/dev".to_string(), InstanceState::Running).into();
+        assert!(registry.update(&running));
+
+        //
+        // Repeated notifications do not change the structure
+        assert!(!registry.update(&running));
+        assert!(!registry
+            .update(&ClassNotification::new("pza/dev/ctrl", vec!["psu".to_string()]).into()));
+
+        let snapshot = registry.snapshot();
+        assert_eq!(snapshot["classes"]["pza/dev/ctrl"]["tags"][0], "psu");
+        assert_eq!(snapshot["attributes"]["pza/dev/ctrl/voltage"]["mode"], "RW");
+        assert_eq!(
+            snapshot["attributes"]["pza/dev/ctrl/voltage"]["type"],
+            "number"
+        );
+
+        let alert = AlertNotification::new("pza/dev/ctrl/voltage", "Overvoltage");
+        registry.update(&alert.clone().into());
+        registry.update(&alert.acknowledged().into());
+        assert_eq!(
+            registry.snapshot()["alarms"]["pza/dev/ctrl/voltage"]["state"],
+            "acknowledged"
+        );
+        registry
+            .update(&AlertNotification::cleared("pza/dev/ctrl/voltage", alert.id.clone()).into());
+        assert!(registry.alarms.is_empty());
+
+        registry.update(&alert.into());
+        registry.remove_instance("pza/dev");
+        assert!(registry.alarms.is_empty());
+        assert!(registry.classes.is_empty());
+        assert!(registry.attributes.is_empty());
+        assert_eq!(registry.instances.len(), 1);
+    }
+}
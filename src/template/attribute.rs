@@ -1,4 +1,296 @@
 pub mod boolean;
 pub mod r#enum;
 pub mod number;
+pub mod string;
 pub mod trigger;
+
+use crate::instance::server::boolean::BooleanAttributeServer;
+use crate::instance::server::number::NumberAttributeServer;
+use crate::instance::server::r#enum::EnumAttributeServer;
+use crate::instance::server::string::StringAttributeServer;
+use crate::model::{BooleanAccessorModel, NumberAccessorModel, StringAccessorModel};
+use crate::AttributeMode;
+use crate::Error;
+use crate::Logger;
+use crate::{log_debug, log_error, Container};
+use async_trait::async_trait;
+use panduza::fbs::{BooleanBuffer, NumberBuffer, StringBuffer};
+use panduza::task_monitor::TaskHandle;
+use std::future::Future;
+use std::pin::Pin;
+
+#[async_trait]
+/// Attribute server whose value is read and written through an accessor of the interface
+///
+pub(crate) trait AccessorServer<I: Send>: Clone + Send + Sync + 'static {
+    /// Value given by the accessor
+    ///
+    type Value: std::fmt::Debug + Send;
+
+    /// Command received by the attribute
+    ///
+    type Command: std::fmt::Debug + Send + 'static;
+
+    /// Read the value from the interface
+    ///
+    async fn read(interface: &mut I, index: usize) -> Result<Self::Value, Error>;
+
+    /// Write the command into the interface
+    ///
+    async fn write(interface: &mut I, index: usize, command: Self::Command) -> Result<(), Error>;
+
+    /// Publish the value on the attribute
+    ///
+    async fn publish(&self, value: Self::Value) -> Result<(), Error>;
+
+    /// Topic of the attribute
+    ///
+    fn topic(&self) -> String;
+
+    /// Logger of the attribute
+    ///
+    fn logger(&self) -> &Logger;
+
+    /// Raise the alert of the attribute
+    ///
+    async fn alert(&self, message: String);
+
+    /// Execute the callback on each command
+    ///
+    async fn on_command<F>(&self, callback: F)
+    where
+        F: Fn(Self::Command) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync + 'static;
+}
+
+/// Bind an attribute to the accessor of the interface at 'index'
+///
+/// Unless the attribute is write only, the value is read at each reset of the parent
+/// by a monitored task. Unless it is read only, each command is written into the
+/// interface and a read write attribute then reads the value back. Failures are
+/// logged and raise the alert of the attribute.
+///
+pub(crate) async fn bind_to_accessor<C, I, A>(
+    parent: &C,
+    att: A,
+    interface: I,
+    index: usize,
+    mode: AttributeMode,
+) where
+    C: Container,
+    I: Clone + Send + Sync + 'static,
+    A: AccessorServer<I>,
+{
+    //
+    // A write only attribute has no value to read
+    if mode != AttributeMode::WriteOnly {
+        let rst = parent.reset_signal();
+        let att2 = att.clone();
+        let mut interface2 = interface.clone();
+        let handle: TaskHandle = tokio::spawn(async move {
+            loop {
+                //
+                // Just init
+                match A::read(&mut interface2, index).await {
+                    Ok(value) => {
+                        log_debug!(att2.logger(), "Initial value ({:?})", &value);
+                        if let Err(e) = att2.publish(value).await {
+                            log_error!(att2.logger(), "initial value not published {:?}", e);
+                            att2.alert(format!("Initial value not published ({:?})", e))
+                                .await;
+                        }
+                    }
+                    Err(e) => {
+                        log_error!(att2.logger(), "initial read failed {:?}", e);
+                        att2.alert(format!("Initial read failed ({:?})", e)).await;
+                    }
+                }
+
+                // Then wait for next reset
+                rst.notified().await;
+            }
+        });
+        parent
+            .monitor_task(format!("{}/INIT", att.topic()), handle)
+            .await;
+    }
+
+    //
+    // Execute the command then read back the value
+    if mode != AttributeMode::ReadOnly {
+        let read_back = mode == AttributeMode::ReadWrite;
+        let att3 = att.clone();
+        att.on_command(move |command: A::Command| {
+            let att = att3.clone();
+            let mut interface = interface.clone();
+            Box::pin(async move {
+                log_debug!(att.logger(), "command received '{:?}'", command);
+                let result = async {
+                    A::write(&mut interface, index, command).await?;
+                    if !read_back {
+                        return Ok(());
+                    }
+                    let read_back_value = A::read(&mut interface, index).await?;
+                    att.publish(read_back_value).await
+                }
+                .await;
+                if let Err(e) = result {
+                    log_error!(att.logger(), "command failed {:?}", e);
+                    att.alert(format!("Command failed ({:?})", e)).await;
+                }
+            })
+        })
+        .await;
+    }
+}
+
+#[async_trait]
+impl<I: BooleanAccessorModel + 'static> AccessorServer<I> for BooleanAttributeServer {
+    type Value = bool;
+    type Command = BooleanBuffer;
+
+    async fn read(interface: &mut I, index: usize) -> Result<bool, Error> {
+        interface.get_boolean_at(index).await
+    }
+
+    async fn write(interface: &mut I, index: usize, command: BooleanBuffer) -> Result<(), Error> {
+        interface.set_boolean_at(index, command.value()).await
+    }
+
+    async fn publish(&self, value: bool) -> Result<(), Error> {
+        self.set(value).await
+    }
+
+    fn topic(&self) -> String {
+        self.inner.topic().clone()
+    }
+
+    fn logger(&self) -> &Logger {
+        self.logger()
+    }
+
+    async fn alert(&self, message: String) {
+        self.trigger_alert(message).await
+    }
+
+    async fn on_command<F>(&self, callback: F)
+    where
+        F: Fn(BooleanBuffer) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync + 'static,
+    {
+        self.add_callback(callback).await;
+    }
+}
+
+#[async_trait]
+impl<I: NumberAccessorModel + 'static> AccessorServer<I> for NumberAttributeServer {
+    type Value = f32;
+    type Command = NumberBuffer;
+
+    async fn read(interface: &mut I, index: usize) -> Result<f32, Error> {
+        interface.get_number_at(index).await
+    }
+
+    async fn write(interface: &mut I, index: usize, command: NumberBuffer) -> Result<(), Error> {
+        interface.set_number_at(index, command.value() as f32).await
+    }
+
+    async fn publish(&self, value: f32) -> Result<(), Error> {
+        self.set(value).await
+    }
+
+    fn topic(&self) -> String {
+        self.inner.topic().clone()
+    }
+
+    fn logger(&self) -> &Logger {
+        self.logger()
+    }
+
+    async fn alert(&self, message: String) {
+        self.trigger_alert(message).await
+    }
+
+    async fn on_command<F>(&self, callback: F)
+    where
+        F: Fn(NumberBuffer) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync + 'static,
+    {
+        self.add_callback(callback).await;
+    }
+}
+
+#[async_trait]
+impl<I: StringAccessorModel + 'static> AccessorServer<I> for StringAttributeServer {
+    type Value = String;
+    type Command = StringBuffer;
+
+    async fn read(interface: &mut I, index: usize) -> Result<String, Error> {
+        interface.get_string_at(index).await
+    }
+
+    async fn write(interface: &mut I, index: usize, command: StringBuffer) -> Result<(), Error> {
+        interface
+            .set_string_at(index, command.value().to_string())
+            .await
+    }
+
+    async fn publish(&self, value: String) -> Result<(), Error> {
+        self.set(value).await
+    }
+
+    fn topic(&self) -> String {
+        self.inner.topic().clone()
+    }
+
+    fn logger(&self) -> &Logger {
+        self.logger()
+    }
+
+    async fn alert(&self, message: String) {
+        self.trigger_alert(message).await
+    }
+
+    async fn on_command<F>(&self, callback: F)
+    where
+        F: Fn(StringBuffer) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync + 'static,
+    {
+        self.add_callback(callback).await;
+    }
+}
+
+#[async_trait]
+impl<I: StringAccessorModel + 'static> AccessorServer<I> for EnumAttributeServer {
+    type Value = String;
+    type Command = StringBuffer;
+
+    async fn read(interface: &mut I, index: usize) -> Result<String, Error> {
+        interface.get_string_at(index).await
+    }
+
+    async fn write(interface: &mut I, index: usize, command: StringBuffer) -> Result<(), Error> {
+        interface
+            .set_string_at(index, command.value().to_string())
+            .await
+    }
+
+    async fn publish(&self, value: String) -> Result<(), Error> {
+        self.set(value).await
+    }
+
+    fn topic(&self) -> String {
+        self.inner.topic().clone()
+    }
+
+    fn logger(&self) -> &Logger {
+        self.logger()
+    }
+
+    async fn alert(&self, message: String) {
+        self.trigger_alert(message).await
+    }
+
+    async fn on_command<F>(&self, callback: F)
+    where
+        F: Fn(StringBuffer) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync + 'static,
+    {
+        self.add_callback(callback).await;
+    }
+}
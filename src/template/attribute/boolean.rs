@@ -1,31 +1,54 @@
+use super::bind_to_accessor;
 use crate::model::BooleanAccessorModel;
+use crate::AttributeMode;
 use crate::Error;
-use crate::{log_debug, log_debug_mount_end, log_debug_mount_start, Container};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use crate::{log_debug_mount_end, log_debug_mount_start, Container};
 
-/// Mount the identity attribute in parent container
+/// Mount a RW boolean attribute in parent container
 ///
 pub async fn mount<
     C: Container,
     I: BooleanAccessorModel + Clone + 'static,
     N: Into<String>,
     F: Into<String>,
+>(
+    parent: C,
+    interface: I,
+    index: usize,
+    name: N,
+    info: F,
+) -> Result<(), Error> {
+    mount_with_mode(
+        parent,
+        interface,
+        index,
+        name,
+        info,
+        AttributeMode::ReadWrite,
+    )
+    .await
+}
+
+/// Mount a boolean attribute with the given mode in parent container
+///
+pub async fn mount_with_mode<
+    C: Container,
+    I: BooleanAccessorModel + Clone + 'static,
+    N: Into<String>,
+    F: Into<String>,
 >(
     mut parent: C,
-    mut interface: I,
+    interface: I,
     index: usize,
     name: N,
     info: F,
+    mode: AttributeMode,
 ) -> Result<(), Error> {
     //
     // Create attribute
-    let mut att = parent
-        .create_attribute(name)
-        .with_rw()
-        .with_info(info)
-        .start_as_boolean()
-        .await?;
+    let mut builder = parent.create_attribute(name).with_info(info);
+    builder.mode = Some(mode.clone());
+    let att = builder.start_as_boolean().await?;
 
     //
     // Create the local logger
@@ -33,43 +56,8 @@ pub async fn mount<
     log_debug_mount_start!(logger);
 
     //
-    //
-    let rst = parent.reset_signal();
-    let logger2 = logger.clone();
-    let att2 = att.clone();
-    let mut interface2 = interface.clone();
-    tokio::spawn(async move {
-        loop {
-            //
-            // Just init
-            let value = interface2.get_boolean_at(index).await.unwrap();
-            log_debug!(logger2, "Initial value ({:?})", &value);
-            att2.set(value).await.unwrap();
-
-            // Then wait for next reset
-            rst.notified().await;
-        }
-    });
-
-    tokio::spawn(async move {
-        loop {
-            att.wait_for_commands().await;
-            while let Some(command) = att.pop().await {
-                //
-                // Log
-                log_debug!(att.logger(), "command received '{:?}'", command);
-
-                //
-                //
-                interface.set_boolean_at(index, command).await.unwrap();
-
-                //
-                // Read back
-                let read_back_value = interface.get_boolean_at(index).await.unwrap();
-                att.set(read_back_value).await.unwrap();
-            }
-        }
-    });
+    // Read the value on each reset and execute the commands
+    bind_to_accessor(&parent, att, interface, index, mode).await;
 
     //
     // End
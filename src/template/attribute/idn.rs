@@ -24,7 +24,7 @@ pub async fn mount<C: Container, I: IdnReader>(
         .create_attribute("identity")
         .with_ro()
         .with_info("Identity string of the device")
-        .finish_as_string()
+        .start_as_string()
         .await?;
 
     //
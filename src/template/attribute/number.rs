@@ -1,12 +1,13 @@
+use super::bind_to_accessor;
+use crate::instance::server::number::NumberSettings;
 use crate::model::NumberAccessorModel;
-use crate::{interface, Error};
-use crate::{log_debug, log_debug_mount_end, log_debug_mount_start, Container};
-use async_trait::async_trait;
-use panduza::fbs::number::NumberBuffer;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use crate::AttributeMode;
+use crate::Error;
+use crate::{log_debug_mount_end, log_debug_mount_start, Container};
 
-/// Mount the identity attribute in parent container
+/// Mount a RW number attribute in parent container
+///
+/// Range and decimals are enforced by the attribute server.
 ///
 pub async fn mount<
     C: Container,
@@ -15,8 +16,8 @@ pub async fn mount<
     F: Into<String>,
     U: Into<String>,
 >(
-    mut parent: C,
-    mut interface: I,
+    parent: C,
+    interface: I,
     index: usize,
     name: N,
     info: F,
@@ -24,15 +25,48 @@ pub async fn mount<
     min: f64,
     max: f64,
     decimals: usize,
+) -> Result<(), Error> {
+    let settings = NumberSettings {
+        min: Some(min),
+        max: Some(max),
+        unit: Some(unit.into()),
+        decimals: Some(decimals),
+        ..Default::default()
+    };
+    mount_with_settings(
+        parent,
+        interface,
+        index,
+        name,
+        info,
+        AttributeMode::ReadWrite,
+        settings,
+    )
+    .await
+}
+
+/// Mount a number attribute with the given mode and constraints in parent container
+///
+pub async fn mount_with_settings<
+    C: Container,
+    I: NumberAccessorModel + Clone + 'static,
+    N: Into<String>,
+    F: Into<String>,
+>(
+    mut parent: C,
+    interface: I,
+    index: usize,
+    name: N,
+    info: F,
+    mode: AttributeMode,
+    settings: NumberSettings,
 ) -> Result<(), Error> {
     //
     // Create attribute
-    let mut att = parent
-        .create_attribute(name)
-        .with_rw()
-        .with_info(info)
-        .start_as_si(unit, min, max, decimals)
-        .await?;
+    let mut builder = parent.create_attribute(name).with_info(info);
+    builder.mode = Some(mode.clone());
+    builder.number_settings = settings;
+    let att = builder.start_as_number().await?;
 
     //
     // Create the local logger
@@ -40,52 +74,9 @@ pub async fn mount<
     log_debug_mount_start!(logger);
 
     //
-    let rst = parent.reset_signal();
-    let logger2 = logger.clone();
-    let att2 = att.clone();
-    let mut interface2 = interface.clone();
-    tokio::spawn(async move {
-        loop {
-            //
-            // Just init
-            let value = interface2.get_number_at(index).await.unwrap();
-            log_debug!(logger2, "Initial value ({:?})", &value);
-            att2.set(NumberBuffer::from_float_with_decimals(value, decimals))
-                .await
-                .unwrap();
-
-            // Then wait for next reset
-            rst.notified().await;
-        }
-    });
-
-    tokio::spawn(async move {
-        loop {
-            att.wait_for_commands().await;
-            while let Some(command) = att.pop().await {
-                //
-                // Log
-                log_debug!(att.logger(), "command received '{:?}'", command);
-
-                //
-                //
-                interface
-                    .set_number_at(index, command.try_into_f32().unwrap())
-                    .await
-                    .unwrap();
+    // Read the value on each reset and execute the commands
+    bind_to_accessor(&parent, att, interface, index, mode).await;
 
-                //
-                // Read back
-                let read_back_value = interface.get_number_at(index).await.unwrap();
-                att.set(NumberBuffer::from_float_with_decimals(
-                    read_back_value,
-                    decimals,
-                ))
-                .await
-                .unwrap();
-            }
-        }
-    });
     //
     // End
     log_debug_mount_end!(logger);
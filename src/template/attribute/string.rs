@@ -0,0 +1,66 @@
+use super::bind_to_accessor;
+use crate::model::StringAccessorModel;
+use crate::AttributeMode;
+use crate::Error;
+use crate::{log_debug_mount_end, log_debug_mount_start, Container};
+
+/// Mount a RW string attribute in parent container
+///
+pub async fn mount<
+    C: Container,
+    I: StringAccessorModel + Clone + 'static,
+    N: Into<String>,
+    F: Into<String>,
+>(
+    parent: C,
+    interface: I,
+    index: usize,
+    name: N,
+    info: F,
+) -> Result<(), Error> {
+    mount_with_mode(
+        parent,
+        interface,
+        index,
+        name,
+        info,
+        AttributeMode::ReadWrite,
+    )
+    .await
+}
+
+/// Mount a string attribute with the given mode in parent container
+///
+pub async fn mount_with_mode<
+    C: Container,
+    I: StringAccessorModel + Clone + 'static,
+    N: Into<String>,
+    F: Into<String>,
+>(
+    mut parent: C,
+    interface: I,
+    index: usize,
+    name: N,
+    info: F,
+    mode: AttributeMode,
+) -> Result<(), Error> {
+    //
+    // Create attribute
+    let mut builder = parent.create_attribute(name).with_info(info);
+    builder.mode = Some(mode.clone());
+    let att = builder.start_as_string().await?;
+
+    //
+    // Create the local logger
+    let logger = att.logger().clone();
+    log_debug_mount_start!(logger);
+
+    //
+    // Read the value on each reset and execute the commands
+    bind_to_accessor(&parent, att, interface, index, mode).await;
+
+    //
+    // End
+    log_debug_mount_end!(logger);
+    Ok(())
+}
@@ -1,10 +1,8 @@
 use crate::model::TriggerAccessorModel;
 use crate::template::class::trigger::{self, Triggerable};
+use crate::Container;
 use crate::Error;
-use crate::{log_debug, log_debug_mount_end, log_debug_mount_start, Container};
 use async_trait::async_trait;
-use std::sync::Arc;
-use tokio::sync::Mutex;
 
 #[derive(Clone)]
 struct TriggerWrap<I: TriggerAccessorModel> {
@@ -28,18 +26,18 @@ pub async fn mount<
     N: Into<String>,
     F: Into<String>,
 >(
-    mut parent: C,
-    mut interface: I,
+    parent: C,
+    interface: I,
     index: usize,
     name: N,
-    info: F,
+    _info: F,
 ) -> Result<(), Error> {
     //
     // Do it like this waiting for better trigger attribute
     let trigger_wrap = TriggerWrap {
         // channel: 0,
         // att: att_data.clone(),
-        interface: interface.clone(),
+        interface: interface,
         index: index,
     };
     trigger::mount(parent, trigger_wrap, name).await?;
@@ -1,5 +1,5 @@
-// pub mod acq_si;
-//pub mod repl;
+pub mod acq_si;
 pub mod boolean_acquisitor;
+pub mod repl;
 pub mod trigger;
 pub mod vectorf32_acquisitor;
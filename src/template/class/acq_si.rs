@@ -1,5 +1,6 @@
 use crate::{
-    log_debug_mount_end, log_debug_mount_start, std::class::trigger, Container, Error, SiAttServer,
+    instance::server::number::NumberAttributeServer, log_debug_mount_end, log_debug_mount_start,
+    template::class::trigger, Container, Error,
 };
 use async_trait::async_trait;
 use std::sync::Arc;
@@ -19,7 +20,7 @@ pub trait SiDataReader: Sync + Send {
 #[derive(Clone)]
 struct TriggerableSi<I: SiDataReader> {
     channel: usize,
-    att: SiAttServer,
+    att: NumberAttributeServer,
     interface: Arc<Mutex<I>>,
 }
 
@@ -27,7 +28,7 @@ struct TriggerableSi<I: SiDataReader> {
 impl<I: SiDataReader> Triggerable for TriggerableSi<I> {
     async fn on_trigger(&mut self) -> Result<(), Error> {
         let value = self.interface.lock().await.read_data(self.channel).await?;
-        self.att.set_from_f32(value as f32).await?;
+        self.att.set(value).await?;
         Ok(())
     }
 }
@@ -46,10 +47,10 @@ pub async fn mount<A: Into<String>, N: Into<String>, C: Container, I: SiDataRead
     //
     //
     let mut class_acq_si = parent
-        .create_class(&name.into())
+        .create_class(name.into())
         .with_tag("acq_si")
         .finish()
-        .await;
+        .await?;
     let logger = class_acq_si.logger().clone();
     log_debug_mount_start!(logger);
 
@@ -58,23 +59,25 @@ pub async fn mount<A: Into<String>, N: Into<String>, C: Container, I: SiDataRead
     let att_data = class_acq_si
         .create_attribute("data")
         .with_ro()
-        .finish_as_si(unit, min, max, decimals)
+        .with_unit(unit)
+        .with_range(min, max)
+        .with_decimals(decimals)
+        .start_as_number()
         .await?;
 
     //
     //
-    let tttt = TriggerableSi {
+    let triggerable = TriggerableSi {
         channel: 0,
-        att: att_data.clone(),
-        interface: interface.clone(),
+        att: att_data,
+        interface: interface,
     };
 
-    trigger::mount(class_acq_si, Arc::new(Mutex::new(tttt))).await?;
-
     // data
     // class trigger
     //    single
     //    cyclic
+    trigger::mount(class_acq_si, triggerable, "trigger").await?;
 
     log_debug_mount_end!(logger);
     Ok(())
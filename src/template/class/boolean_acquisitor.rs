@@ -3,8 +3,6 @@ use crate::{
     model::BooleanAccessorModel, template::class::trigger, Container, Error,
 };
 use async_trait::async_trait;
-use std::sync::Arc;
-use tokio::sync::Mutex;
 
 use super::trigger::Triggerable;
 
@@ -28,7 +26,7 @@ impl<I: BooleanAccessorModel> Triggerable for TriggerWrap<I> {
 ///
 pub async fn mount<A: Into<String>, C: Container, I: BooleanAccessorModel + Clone + 'static>(
     mut parent: C,
-    mut interface: I,
+    interface: I,
     index: usize,
     name: A,
 ) -> Result<(), Error> {
@@ -38,7 +36,7 @@ pub async fn mount<A: Into<String>, C: Container, I: BooleanAccessorModel + Clon
         .create_class(&name.into())
         .with_tag("boolean_acquisitor")
         .finish()
-        .await;
+        .await?;
     let logger = top_class.logger().clone();
     log_debug_mount_start!(logger);
 
@@ -55,7 +53,7 @@ pub async fn mount<A: Into<String>, C: Container, I: BooleanAccessorModel + Clon
     let trigger_wrap = TriggerWrap {
         // channel: 0,
         // att: att_data.clone(),
-        interface: interface.clone(),
+        interface: interface,
         index: index,
         att_srv: att_data,
     };
@@ -1,8 +1,6 @@
-use crate::{
-    log_debug, log_debug_mount_end, log_debug_mount_start, spawn_on_command, Container, Error,
-    Logger, StringAttServer,
-};
+use crate::{log_debug, log_debug_mount_end, log_debug_mount_start, log_error, Container, Error};
 use async_trait::async_trait;
+use panduza::fbs::StringBuffer;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -15,7 +13,7 @@ pub trait ReplProtocol: Sync + Send {
     async fn eval(&mut self, command: String) -> Result<String, Error>;
 }
 
-/// Mount the identity attribute
+/// Mount the REPL class (WO command, RO response)
 ///
 pub async fn mount<A: Into<String>, C: Container, I: ReplProtocol + 'static>(
     name: A,
@@ -25,62 +23,53 @@ pub async fn mount<A: Into<String>, C: Container, I: ReplProtocol + 'static>(
     //
     //
     let mut class_repl = parent
-        .create_class(&name.into())
+        .create_class(name.into())
         .with_tag("REPL")
         .finish()
-        .await;
+        .await?;
     let logger = class_repl.logger().clone();
     log_debug_mount_start!(logger);
 
     let att_command = class_repl
         .create_attribute("command")
         .with_wo()
-        .finish_as_string()
+        .start_as_string()
         .await?;
 
     let att_response = class_repl
         .create_attribute("response")
         .with_ro()
-        .finish_as_string()
+        .start_as_string()
         .await?;
 
     //
     // Execute action on each command received
     let logger_2 = att_command.logger().clone();
-    let att_command_2 = att_command.clone();
-    let att_response_2 = att_response.clone();
-    spawn_on_command!(
-        "on_command => relp",
-        parent,
-        att_command_2,
-        on_command(
-            logger_2.clone(),
-            att_command_2.clone(),
-            att_response_2.clone(),
-            connector.clone()
-        )
-    );
+    att_command
+        .add_callback(move |command: StringBuffer| {
+            let logger = logger_2.clone();
+            let att_response = att_response.clone();
+            let connector = connector.clone();
+            Box::pin(async move {
+                let command = command.value().to_string();
+                log_debug!(logger, "Command received {:?}", command);
+                let result = async {
+                    let response = connector.lock().await.eval(command).await?;
+                    att_response.set(response).await
+                }
+                .await;
+                if let Err(e) = result {
+                    log_error!(logger, "Command failed {:?}", e);
+                    att_response
+                        .trigger_alert(format!("Command failed ({:?})", e))
+                        .await;
+                }
+            })
+        })
+        .await;
 
     //
     // End
     log_debug_mount_end!(logger);
     Ok(())
 }
-
-/// On command callback
-///
-async fn on_command<I: ReplProtocol + 'static>(
-    logger: Logger,
-    mut att_command: StringAttServer,
-    att_response: StringAttServer,
-    connector: Arc<Mutex<I>>,
-) -> Result<(), Error> {
-    while let Some(command) = att_command.pop_cmd().await {
-        //
-        // Log
-        log_debug!(logger, "Command received {:?}", command);
-        let response = connector.lock().await.eval(command).await?;
-        att_response.set(response).await?;
-    }
-    Ok(())
-}
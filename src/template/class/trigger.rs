@@ -1,13 +1,17 @@
-use crate::{
-    log_debug, log_debug_mount_end, log_debug_mount_start, log_trace, model::BooleanAccessorModel,
-    Container, Error,
-};
+use crate::instance::server::number::NumberAttributeServer;
+use crate::Logger;
+use crate::{log_debug, log_debug_mount_end, log_debug_mount_start, log_error, log_trace};
+use crate::{Container, Error};
 use async_trait::async_trait;
-use std::{sync::Arc, time::Duration};
-use tokio::{
-    sync::{Mutex, Notify},
-    time::sleep,
-};
+use panduza::fbs::{BooleanBuffer, NumberBuffer};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::{sleep_until, Instant};
+
+/// Period between two publications of the cycle statistics
+///
+const STATISTICS_PUBLICATION_PERIOD: Duration = Duration::from_secs(1);
 
 #[async_trait]
 ///
@@ -18,11 +22,67 @@ pub trait Triggerable: Sync + Send {
     async fn on_trigger(&mut self) -> Result<(), Error>;
 }
 
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Timing statistics of the cyclic trigger
+///
+pub struct CycleStatistics {
+    /// Delay between the planned start and the real start of the last cycle (ms)
+    ///
+    pub jitter: f64,
+
+    /// Worst jitter since the last change of frequency (ms)
+    ///
+    pub jitter_max: f64,
+
+    /// Number of cycles skipped because a trigger lasted longer than the period
+    ///
+    pub overruns: u64,
+}
+
+impl CycleStatistics {
+    /// Record a cycle and return the planned start of the next one
+    ///
+    /// When the trigger ends after the next planned start, the missed cycles
+    /// are counted as overruns and skipped.
+    ///
+    pub fn record(
+        &mut self,
+        planned: Instant,
+        started: Instant,
+        ended: Instant,
+        period: Duration,
+    ) -> Instant {
+        let jitter = started.saturating_duration_since(planned).as_nanos() as f64 / 1e6;
+        self.jitter = jitter;
+        self.jitter_max = self.jitter_max.max(jitter);
+
+        let next = planned + period;
+        if ended <= next {
+            return next;
+        }
+        let late = ended.duration_since(planned).as_nanos() / period.as_nanos().max(1);
+        let cycles = u32::try_from(late).unwrap_or(u32::MAX - 1);
+        self.overruns += cycles as u64;
+        planned + period * (cycles + 1)
+    }
+}
+
+/// Mount a trigger class in the parent container
+///
+/// ## Attributes
+///
+/// - single: trigger once on each command
+/// - cyclic: frequency of the automatic trigger (Hz), 0 to stop it
+/// - jitter: delay of the last cyclic trigger from its planned start (ms)
+/// - jitter_max: worst jitter since the last change of frequency (ms)
+/// - overruns: cycles skipped because the trigger lasted longer than the period
 ///
+/// The cyclic task is monitored by the instance. A trigger failure ends it and
+/// moves the instance to Error, the instance then cancels it until the next mount.
 ///
 pub async fn mount<C: Container, I: Triggerable + Clone + 'static, N: Into<String>>(
     mut parent: C,
-    mut interface: I,
+    interface: I,
     name: N,
 ) -> Result<(), Error> {
     //
@@ -31,13 +91,13 @@ pub async fn mount<C: Container, I: Triggerable + Clone + 'static, N: Into<Strin
         .create_class(name.into())
         .with_tag("trigger")
         .finish()
-        .await;
+        .await?;
     let logger = class_trigger.logger().clone();
     log_debug_mount_start!(logger);
 
     //
     //
-    let mut att_single = class_trigger
+    let att_single = class_trigger
         .create_attribute("single")
         .with_wo()
         .start_as_boolean()
@@ -45,71 +105,188 @@ pub async fn mount<C: Container, I: Triggerable + Clone + 'static, N: Into<Strin
 
     //
     // Execute action on each command received
-    let mut interface_2 = interface.clone();
-    tokio::spawn(async move {
-        loop {
-            att_single.wait_for_commands().await;
-            while let Some(command) = att_single.pop().await {
-                interface_2.on_trigger().await.unwrap();
-            }
-        }
-    });
+    let interface_2 = interface.clone();
+    let att_single_2 = att_single.clone();
+    att_single
+        .add_callback(move |_command: BooleanBuffer| {
+            let mut interface = interface_2.clone();
+            let att_single = att_single_2.clone();
+            Box::pin(async move {
+                if let Err(e) = interface.on_trigger().await {
+                    log_error!(att_single.logger(), "trigger failed {:?}", e);
+                    att_single
+                        .trigger_alert(format!("Trigger failed ({:?})", e))
+                        .await;
+                }
+            })
+        })
+        .await;
 
     //
     //
-    let mut att_cyclic = class_trigger
+    let att_cyclic = class_trigger
         .create_attribute("cyclic")
         .with_rw()
-        .start_as_si("Hz", 0.0, 300.0, 2)
+        .with_info("Frequency of the automatic trigger, 0 to stop it")
+        .with_unit("Hz")
+        .with_range(0.0, 300.0)
+        .with_decimals(2)
+        .start_as_number()
         .await?;
+    att_cyclic.set(0.0).await?;
 
     //
-    //
-    let cycle_changed = Arc::new(Notify::new());
-    let cycle_freq = Arc::new(Mutex::new(0.0));
-    let mut cycle_step = Duration::from_secs(0xFFFFFFFF);
+    // Statistics of the cyclic trigger
+    let att_jitter = class_trigger
+        .create_attribute("jitter")
+        .with_ro()
+        .with_info("Delay of the last cyclic trigger from its planned start")
+        .with_unit("ms")
+        .with_decimals(3)
+        .start_as_number()
+        .await?;
+    let att_jitter_max = class_trigger
+        .create_attribute("jitter_max")
+        .with_ro()
+        .with_info("Worst jitter since the last change of frequency")
+        .with_unit("ms")
+        .with_decimals(3)
+        .start_as_number()
+        .await?;
+    let att_overruns = class_trigger
+        .create_attribute("overruns")
+        .with_ro()
+        .with_info("Cycles skipped because the trigger lasted longer than the period")
+        .with_decimals(0)
+        .start_as_number()
+        .await?;
+    let statistics_atts = [att_jitter, att_jitter_max, att_overruns];
+    publish_statistics(&statistics_atts, &CycleStatistics::default()).await?;
 
     //
+    // The latest frequency is kept even if it changes during a trigger
+    let (cycle_freq_sender, cycle_freq) = watch::channel(0.0);
+    let cycle_freq_sender = Arc::new(cycle_freq_sender);
+    let att_cyclic_2 = att_cyclic.clone();
+    att_cyclic
+        .add_callback(move |command: NumberBuffer| {
+            let cycle_freq_sender = cycle_freq_sender.clone();
+            let att_cyclic = att_cyclic_2.clone();
+            Box::pin(async move {
+                let freq = command.value();
+                cycle_freq_sender.send_replace(freq);
+                if let Err(e) = att_cyclic.set(freq).await {
+                    log_error!(att_cyclic.logger(), "cycle not updated {:?}", e);
+                }
+            })
+        })
+        .await;
+
     //
-    let cycle_freq_2 = cycle_freq.clone();
-    let att_cyclic_logger = att_cyclic.logger().clone();
-    let mut att_cyclic_2 = att_cyclic.clone();
-    let cycle_changed_2 = cycle_changed.clone();
-    tokio::spawn(async move {
-        loop {
-            att_cyclic_2.wait_for_commands().await;
-            while let Some(command) = att_cyclic_2.pop().await {
-                *cycle_freq_2.lock().await = command.try_into_f32().unwrap();
-                att_cyclic_2.set(command).await.unwrap();
-                cycle_changed_2.notify_waiters();
-            }
-        }
-    });
+    // The instance cancels this task when it goes to Error
+    let handle = tokio::spawn(task_cycle(
+        att_cyclic.logger().clone(),
+        interface,
+        cycle_freq,
+        statistics_atts,
+    ));
+    class_trigger
+        .monitor_task(format!("{}/CYCLE", class_trigger.topic()), handle)
+        .await;
 
     //
     //
-    tokio::spawn(async move {
-        loop {
-            tokio::select! {
-                _ = cycle_changed.notified() => {
-                    let freq = *cycle_freq.lock().await;
-                    cycle_step = if freq > 0.0 {
-                        Duration::from_secs_f64(1.0 / freq as f64)
-                    } else {
-                        Duration::from_secs(0xFFFFFFFF)
-                    };
-                    log_debug!(att_cyclic_logger, "cycle changed {:?}Hz => {:?}", freq, cycle_step);
+    log_debug_mount_end!(logger);
+    Ok(())
+}
+
+/// Trigger the interface at the frequency of the cyclic attribute
+///
+async fn task_cycle<I: Triggerable>(
+    logger: Logger,
+    mut interface: I,
+    mut cycle_freq: watch::Receiver<f64>,
+    statistics_atts: [NumberAttributeServer; 3],
+) -> Result<(), String> {
+    let mut period: Option<Duration> = None;
+    let mut planned = Instant::now();
+    let mut statistics = CycleStatistics::default();
+    let mut last_publication = Instant::now();
+    loop {
+        tokio::select! {
+            changed = cycle_freq.changed() => {
+                //
+                // The attribute has been dropped
+                if changed.is_err() {
+                    return Ok(());
                 }
-                _ = sleep(cycle_step) => {
-                    log_trace!(att_cyclic_logger, "auto trig !");
-                    interface.on_trigger().await.unwrap();
+                let freq = *cycle_freq.borrow_and_update();
+                period = (freq > 0.0).then(|| Duration::from_secs_f64(1.0 / freq));
+                planned = Instant::now() + period.unwrap_or_default();
+                statistics = CycleStatistics::default();
+                publish_statistics(&statistics_atts, &statistics)
+                    .await
+                    .map_err(|e| format!("{:?}", e))?;
+                log_debug!(logger, "cycle changed {:?}Hz => {:?}", freq, period);
+            }
+            _ = sleep_until(planned), if period.is_some() => {
+                let started = Instant::now();
+                log_trace!(logger, "auto trig !");
+                interface
+                    .on_trigger()
+                    .await
+                    .map_err(|e| format!("Cyclic trigger failed ({:?})", e))?;
+                planned = statistics.record(planned, started, Instant::now(), period.unwrap());
+
+                //
+                // Do not flood the platform at high frequencies
+                if last_publication.elapsed() >= STATISTICS_PUBLICATION_PERIOD {
+                    publish_statistics(&statistics_atts, &statistics)
+                        .await
+                        .map_err(|e| format!("{:?}", e))?;
+                    last_publication = Instant::now();
                 }
             }
         }
-    });
+    }
+}
 
-    //
-    //
-    log_debug_mount_end!(logger);
-    Ok(())
+/// Publish the statistics on the jitter, jitter_max and overruns attributes
+///
+async fn publish_statistics(
+    atts: &[NumberAttributeServer; 3],
+    statistics: &CycleStatistics,
+) -> Result<(), Error> {
+    atts[0].set(statistics.jitter).await?;
+    atts[1].set(statistics.jitter_max).await?;
+    atts[2].set(statistics.overruns as f64).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle_statistics() {
+        let period = Duration::from_millis(10);
+        let planned = Instant::now();
+        let mut statistics = CycleStatistics::default();
+
+        //
+        // On time
+        let started = planned + Duration::from_millis(2);
+        let next = statistics.record(planned, started, started, period);
+        assert_eq!(next, planned + period);
+        assert_eq!(statistics.jitter, 2.0);
+        assert_eq!(statistics.overruns, 0);
+
+        //
+        // The trigger lasts 2.5 periods, 2 cycles are skipped
+        let ended = next + Duration::from_millis(25);
+        let after = statistics.record(next, next, ended, period);
+        assert_eq!(after, next + Duration::from_millis(30));
+        assert_eq!(statistics.jitter, 0.0);
+        assert_eq!(statistics.jitter_max, 2.0);
+        assert_eq!(statistics.overruns, 2);
+    }
 }
@@ -1,11 +1,8 @@
 use crate::{
-    instance::server::vector_f32_v0::VectorF32AttributeServer, log_debug_mount_end,
-    log_debug_mount_start, model::VectorF32AccessorModel, template::class::trigger, Container,
-    Error,
+    instance::server::vector::VectorF32AttributeServer, log_debug_mount_end, log_debug_mount_start,
+    model::VectorF32AccessorModel, template::class::trigger, Container, Error,
 };
 use async_trait::async_trait;
-use std::sync::Arc;
-use tokio::sync::Mutex;
 
 use super::trigger::Triggerable;
 
@@ -29,7 +26,7 @@ impl<I: VectorF32AccessorModel> Triggerable for TriggerWrap<I> {
 ///
 pub async fn mount<A: Into<String>, C: Container, I: VectorF32AccessorModel + Clone + 'static>(
     mut parent: C,
-    mut interface: I,
+    interface: I,
     index: usize,
     name: A,
 ) -> Result<(), Error> {
@@ -39,7 +36,7 @@ pub async fn mount<A: Into<String>, C: Container, I: VectorF32AccessorModel + Cl
         .create_class(&name.into())
         .with_tag("vectorf32_acquisitor")
         .finish()
-        .await;
+        .await?;
     let logger = top_class.logger().clone();
     log_debug_mount_start!(logger);
 
@@ -59,7 +56,7 @@ pub async fn mount<A: Into<String>, C: Container, I: VectorF32AccessorModel + Cl
     let trigger_wrap = TriggerWrap {
         // channel: 0,
         // att: att_data.clone(),
-        interface: interface.clone(),
+        interface: interface,
         index: index,
         att_srv: att_data,
     };
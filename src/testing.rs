@@ -0,0 +1,517 @@
+use crate::engine::EngineBuilder;
+use crate::Engine;
+use crate::Error;
+use crate::Factory;
+use crate::Notification;
+use crate::NotificationGroup;
+use crate::ProductionOrder;
+use crate::RuntimeBuilder;
+use panduza::fbs::PzaBuffer;
+use panduza::InstanceState;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout_at, Instant};
+
+/// Namespace used by default by the test benches
+///
+pub static TEST_BENCH_NAMESPACE: &str = "test";
+
+/// Period used to poll the notifications of the runtime
+///
+const NOTIFICATION_POLL_PERIOD: Duration = Duration::from_millis(10);
+
+/// Runtime running on a loopback engine to test drivers without a zenoh router
+///
+/// ## Example
+///
+/// ```ignore
+/// let mut bench = TestBench::start(factory).await;
+/// bench.produce(ProductionOrder::new("vendor.model", "dev")).await?;
+/// bench.wait_state("dev", InstanceState::Running, Duration::from_secs(1)).await?;
+/// bench.publish_command("dev/voltage", NumberBuffer::from(3.3)).await?;
+/// let value: NumberBuffer = bench.query_attribute("dev/voltage").await?;
+/// ```
+///
+pub struct TestBench {
+    /// Engine shared with the runtime
+    ///
+    engine: Engine,
+
+    /// To send production orders to the runtime
+    ///
+    production_orders: Sender<ProductionOrder>,
+
+    /// Notifications stored by the runtime
+    ///
+    notifications: Arc<std::sync::Mutex<NotificationGroup>>,
+
+    /// Notifications already pulled from the runtime
+    ///
+    received: Vec<Notification>,
+
+    /// Task of the runtime, aborted when the bench is dropped
+    ///
+    runtime_handle: JoinHandle<Result<(), Error>>,
+}
+
+impl TestBench {
+    /// Start a runtime with the given factory on a loopback engine
+    ///
+    pub async fn start(factory: Factory) -> Self {
+        Self::start_with_namespace(factory, TEST_BENCH_NAMESPACE).await
+    }
+
+    /// Same as `start` but with a custom namespace
+    ///
+    pub async fn start_with_namespace<A: Into<String>>(factory: Factory, namespace: A) -> Self {
+        let engine_builder = EngineBuilder::new_loopback(Some(namespace.into()));
+        let (runtime_builder, production_orders) = RuntimeBuilder::new(factory, engine_builder);
+        let notifications = runtime_builder.clone_notifications();
+
+        let runtime = runtime_builder.start().await;
+        let engine = runtime.engine().clone();
+        let runtime_handle = tokio::spawn(runtime.task());
+
+        Self {
+            engine: engine,
+            production_orders: production_orders,
+            notifications: notifications,
+            received: Vec::new(),
+            runtime_handle: runtime_handle,
+        }
+    }
+
+    /// Engine used by the runtime
+    ///
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Full topic from a path relative to the root topic ("instance/class/attribute")
+    ///
+    pub fn topic<A: AsRef<str>>(&self, path: A) -> String {
+        format!(
+            "{}/{}",
+            self.engine.root_topic(self.engine.namespace.clone()),
+            path.as_ref()
+        )
+    }
+
+    /// Ask the runtime to produce an instance
+    ///
+    pub async fn produce(&self, order: ProductionOrder) -> Result<(), Error> {
+        self.production_orders
+            .send(order)
+            .await
+            .map_err(|e| Error::ChannelError(format!("Runtime unreachable ({:?})", e)))
+    }
+
+    /// Publish a command on '<topic>/cmd', 'path' is relative to the root topic
+    ///
+    pub async fn publish_command<A: AsRef<str>, B: PzaBuffer>(
+        &self,
+        path: A,
+        command: B,
+    ) -> Result<(), Error> {
+        self.engine
+            .session
+            .put(format!("{}/cmd", self.topic(path)), command.to_zbytes())
+            .await
+            .map_err(|e| Error::InternalLogic(format!("Failed to publish command ({})", e)))
+    }
+
+    /// Query the current value of '<topic>/att', 'path' is relative to the root topic
+    ///
+    pub async fn query_attribute<A: AsRef<str>, B: PzaBuffer>(&self, path: A) -> Result<B, Error> {
+        let att_topic = format!("{}/att", self.topic(path));
+        let replies = self.engine.session.get(&att_topic).await.map_err(|e| {
+            Error::InternalLogic(format!("Failed to query '{}' ({})", att_topic, e))
+        })?;
+
+        while let Ok(reply) = replies.recv_async().await {
+            if let Ok(sample) = reply.result() {
+                return Ok(B::from_zbytes(sample.payload().clone()));
+            }
+        }
+        Err(Error::InternalLogic(format!("No reply on '{}'", att_topic)))
+    }
+
+    /// Publish a command and return the next value published by the attribute
+    ///
+    /// Values received before the command is sent are ignored, they are stale.
+    ///
+    pub async fn expect_attribute<A: AsRef<str>, B: PzaBuffer, C: PzaBuffer>(
+        &self,
+        path: A,
+        command: C,
+        max_wait: Duration,
+    ) -> Result<B, Error> {
+        let path = path.as_ref();
+        let att_topic = format!("{}/att", self.topic(path));
+
+        //
+        // Stamp each sample with its reception time
+        let (sample_sender, mut samples) = tokio::sync::mpsc::unbounded_channel();
+        let _subscriber = self
+            .engine
+            .session
+            .declare_subscriber(&att_topic)
+            .callback(move |sample| {
+                let _ = sample_sender.send((std::time::Instant::now(), sample));
+            })
+            .await
+            .map_err(|e| {
+                Error::InternalLogic(format!("Failed to subscribe '{}' ({})", att_topic, e))
+            })?;
+
+        let sent_at = std::time::Instant::now();
+        self.publish_command(path, command).await?;
+
+        let deadline = Instant::now() + max_wait;
+        loop {
+            let (received_at, sample) = timeout_at(deadline, samples.recv())
+                .await
+                .map_err(|_| Error::Timeout(format!("No value on '{}'", att_topic)))?
+                .ok_or_else(|| Error::InternalLogic("Subscriber closed".to_string()))?;
+            if received_at >= sent_at {
+                return Ok(B::from_zbytes(sample.payload().clone()));
+            }
+        }
+    }
+
+    /// All the notifications emitted by the runtime since the start of the bench
+    ///
+    pub fn notifications(&mut self) -> &Vec<Notification> {
+        self.pull_notifications();
+        &self.received
+    }
+
+    /// Wait until a notification matches the predicate
+    ///
+    /// Notifications already received are checked too.
+    ///
+    pub async fn wait_notification<F>(
+        &mut self,
+        predicate: F,
+        max_wait: Duration,
+    ) -> Result<Notification, Error>
+    where
+        F: Fn(&Notification) -> bool,
+    {
+        let deadline = Instant::now() + max_wait;
+        loop {
+            self.pull_notifications();
+            if let Some(found) = self.received.iter().find(|n| predicate(n)) {
+                return Ok(found.clone());
+            }
+            if Instant::now() >= deadline {
+                return Err(Error::Timeout(format!(
+                    "No matching notification after {:?}",
+                    max_wait
+                )));
+            }
+            sleep(NOTIFICATION_POLL_PERIOD).await;
+        }
+    }
+
+    /// Wait until the instance reaches the given state
+    ///
+    pub async fn wait_state<A: AsRef<str>>(
+        &mut self,
+        instance: A,
+        state: InstanceState,
+        max_wait: Duration,
+    ) -> Result<(), Error> {
+        let topic = self.topic(instance);
+        let expected = std::mem::discriminant(&state);
+        self.wait_notification(
+            |n| match n {
+                Notification::State(s) => {
+                    s.topic == topic && std::mem::discriminant(&s.state) == expected
+                }
+                _ => false,
+            },
+            max_wait,
+        )
+        .await
+        .map(|_| ())
+        .map_err(|_| Error::Timeout(format!("'{}' never reached state {}", topic, state)))
+    }
+
+    /// Move the notifications of the runtime into the bench
+    ///
+    fn pull_notifications(&mut self) {
+        let pulled = self.notifications.lock().unwrap().pull();
+        self.received
+            .extend(pulled.into_iter().map(|n| n.notification));
+    }
+}
+
+impl Drop for TestBench {
+    fn drop(&mut self) {
+        self.runtime_handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::BooleanAccessorModel;
+    use crate::template::attribute::boolean;
+    use crate::{AccessorModel, Actions, AttributeMode, Instance, Producer, Props};
+    use async_trait::async_trait;
+    use panduza::fbs::BooleanBuffer;
+    use tokio::sync::Mutex;
+
+    /// Device with a single boolean
+    ///
+    #[derive(Clone, Default)]
+    struct FakeDevice {
+        enable: Arc<Mutex<bool>>,
+    }
+
+    #[async_trait]
+    impl BooleanAccessorModel for FakeDevice {
+        async fn get_boolean_at(&mut self, _index: usize) -> Result<bool, Error> {
+            Ok(*self.enable.lock().await)
+        }
+        async fn set_boolean_at(&mut self, _index: usize, value: bool) -> Result<(), Error> {
+            *self.enable.lock().await = value;
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Actions for FakeDevice {
+        async fn mount(&mut self, instance: Instance) -> Result<(), Error> {
+            boolean::mount(instance, self.clone(), 0, "enable", "output enable").await
+        }
+        async fn wait_reboot_event(&mut self, _instance: Instance) {
+            std::future::pending::<()>().await
+        }
+    }
+
+    struct FakeProducer;
+
+    impl Producer for FakeProducer {
+        fn manufacturer(&self) -> String {
+            "fake".to_string()
+        }
+        fn model(&self) -> String {
+            "device".to_string()
+        }
+        fn description(&self) -> String {
+            "fake device".to_string()
+        }
+        fn props(&self) -> Props {
+            Props::default()
+        }
+        fn produce(&self) -> Result<Box<dyn Actions>, Error> {
+            Ok(Box::new(FakeDevice::default()))
+        }
+    }
+
+    /// Supply driver described by the derive macro
+    ///
+    #[derive(Clone, Default, AccessorModel)]
+    struct FakeSupply {
+        #[attribute(
+            kind = "number",
+            class = "output",
+            unit = "V",
+            min = 0,
+            max = 30,
+            decimals = 2,
+            getter = "voltage",
+            setter = "set_voltage"
+        )]
+        voltage: Arc<Mutex<f64>>,
+
+        #[attribute(
+            kind = "enum",
+            class = "output",
+            choices("cc", "cv"),
+            getter = "regulation"
+        )]
+        regulation: Arc<Mutex<String>>,
+
+        #[attribute(info = "Model of the supply")]
+        model: String,
+    }
+
+    impl FakeSupply {
+        async fn voltage(&mut self) -> Result<f64, Error> {
+            Ok(*self.voltage.lock().await)
+        }
+        async fn set_voltage(&mut self, value: f64) -> Result<(), Error> {
+            *self.voltage.lock().await = value;
+            Ok(())
+        }
+        async fn regulation(&mut self) -> Result<String, Error> {
+            Ok(self.regulation.lock().await.clone())
+        }
+    }
+
+    #[async_trait]
+    impl Actions for FakeSupply {
+        async fn mount(&mut self, instance: Instance) -> Result<(), Error> {
+            *self.regulation.lock().await = "cv".to_string();
+            self.mount_all(instance).await
+        }
+        async fn wait_reboot_event(&mut self, _instance: Instance) {
+            std::future::pending::<()>().await
+        }
+    }
+
+    struct FakeSupplyProducer;
+
+    impl Producer for FakeSupplyProducer {
+        fn manufacturer(&self) -> String {
+            "fake".to_string()
+        }
+        fn model(&self) -> String {
+            "supply".to_string()
+        }
+        fn description(&self) -> String {
+            "fake supply".to_string()
+        }
+        fn props(&self) -> Props {
+            Props::default()
+        }
+        fn produce(&self) -> Result<Box<dyn Actions>, Error> {
+            Ok(Box::new(FakeSupply::default()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bench_derived_driver_structure() {
+        let mut factory = Factory::new();
+        factory.add_producer(Box::new(FakeSupplyProducer));
+        let mut bench = TestBench::start(factory).await;
+
+        bench
+            .produce(ProductionOrder::new("fake.supply", "sup"))
+            .await
+            .unwrap();
+        bench
+            .wait_state("sup", InstanceState::Running, Duration::from_secs(2))
+            .await
+            .unwrap();
+
+        //
+        // The class declared on the fields
+        let topic = bench.topic("sup/output");
+        bench
+            .wait_notification(
+                |n| matches!(n, Notification::Class(c) if c.topic == topic),
+                Duration::from_secs(1),
+            )
+            .await
+            .unwrap();
+
+        //
+        // Number with its settings
+        let topic = bench.topic("sup/output/voltage");
+        let Notification::Attribute(voltage) = bench
+            .wait_notification(
+                |n| matches!(n, Notification::Attribute(a) if a.topic() == topic),
+                Duration::from_secs(1),
+            )
+            .await
+            .unwrap()
+        else {
+            unreachable!()
+        };
+        assert_eq!(voltage.typee, "number");
+        assert_eq!(*voltage.mode(), AttributeMode::ReadWrite);
+        assert_eq!(
+            *voltage.settings(),
+            Some(serde_json::json!({ "min": 0.0, "max": 30.0, "unit": "V", "decimals": 2 }))
+        );
+
+        //
+        // Enum with its choices, read only without setter
+        let topic = bench.topic("sup/output/regulation");
+        let Notification::Attribute(regulation) = bench
+            .wait_notification(
+                |n| matches!(n, Notification::Attribute(a) if a.topic() == topic),
+                Duration::from_secs(1),
+            )
+            .await
+            .unwrap()
+        else {
+            unreachable!()
+        };
+        assert_eq!(regulation.typee, "enum");
+        assert_eq!(*regulation.mode(), AttributeMode::ReadOnly);
+        assert_eq!(
+            *regulation.settings(),
+            Some(serde_json::json!({ "choices": ["cc", "cv"] }))
+        );
+
+        //
+        // Field backed string, in the instance itself
+        let topic = bench.topic("sup/model");
+        let Notification::Attribute(model) = bench
+            .wait_notification(
+                |n| matches!(n, Notification::Attribute(a) if a.topic() == topic),
+                Duration::from_secs(1),
+            )
+            .await
+            .unwrap()
+        else {
+            unreachable!()
+        };
+        assert_eq!(model.typee, "string");
+        assert_eq!(*model.mode(), AttributeMode::ReadOnly);
+    }
+
+    #[tokio::test]
+    async fn test_bench_command_and_notifications() {
+        let mut factory = Factory::new();
+        factory.add_producer(Box::new(FakeProducer));
+        let mut bench = TestBench::start(factory).await;
+
+        bench
+            .produce(ProductionOrder::new("fake.device", "dev"))
+            .await
+            .unwrap();
+        bench
+            .wait_state("dev", InstanceState::Running, Duration::from_secs(2))
+            .await
+            .unwrap();
+
+        //
+        // Let the initial value go out before listening the attribute
+        sleep(Duration::from_millis(100)).await;
+
+        //
+        // The command is executed then the value read back
+        let command = BooleanBuffer::builder()
+            .with_value(true)
+            .with_source(0)
+            .with_random_sequence()
+            .build()
+            .unwrap();
+        let reply: BooleanBuffer = bench
+            .expect_attribute("dev/enable", command, Duration::from_secs(2))
+            .await
+            .unwrap();
+        assert!(reply.value());
+        let value: BooleanBuffer = bench.query_attribute("dev/enable").await.unwrap();
+        assert!(value.value());
+
+        //
+        // The attribute has been declared to the platform
+        let topic = bench.topic("dev/enable");
+        bench
+            .wait_notification(
+                |n| matches!(n, Notification::Attribute(a) if a.topic() == topic),
+                Duration::from_secs(1),
+            )
+            .await
+            .unwrap();
+    }
+}
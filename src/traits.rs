@@ -1,22 +1,8 @@
-use crate::{Error, Instance, ProductionOrder, Props};
+use crate::{Error, Instance, Props};
 use async_trait::async_trait;
 use bytes::Bytes;
 use std::fmt::Debug;
 
-///
-///
-///
-pub trait Scanner: Send {
-    ///
-    ///
-    ///
-    fn name(&self) -> String;
-    ///
-    ///
-    ///
-    fn scan(&self) -> Vec<ProductionOrder>;
-}
-
 /// Trait to manage an message attribute (MQTT)
 /// Sync version
 #[async_trait]